@@ -0,0 +1,63 @@
+//! Per chat subscriptions notified when the best rate crosses a threshold.
+
+use crate::source::{Currency, RateType};
+use rust_decimal::Decimal;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumString, strum::Display)]
+pub enum Op {
+    #[strum(serialize = ">")]
+    Above,
+    #[strum(serialize = "<")]
+    Below,
+}
+
+impl Op {
+    pub fn is_met(&self, rate: Decimal, value: Decimal) -> bool {
+        match self {
+            Self::Above => rate > value,
+            Self::Below => rate < value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub id: i64,
+    pub chat_id: i64,
+    pub from: Currency,
+    pub to: Currency,
+    pub op: Op,
+    pub value: Decimal,
+    pub rate_type: RateType,
+    /// The condition was met on the last check, cleared once it is not, so
+    /// that every crossing is notified once.
+    pub triggered: bool,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {}/{} {} {} {:?}",
+            self.id, self.from, self.to, self.op, self.value, self.rate_type
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_op() {
+        assert_eq!(Op::from_str(">"), Ok(Op::Above));
+        assert_eq!(Op::from_str("<"), Ok(Op::Below));
+        assert!(Op::from_str("=").is_err());
+        assert!(Op::Above.is_met(dec!(4.91), dec!(4.9)));
+        assert!(!Op::Above.is_met(dec!(4.9), dec!(4.9)));
+        assert!(Op::Below.is_met(dec!(4.89), dec!(4.9)));
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
-    config::Config,
-    database::Database,
+    alert::Op,
+    chart,
+    config::{self, Config, TableFormat},
+    database::{Database, Status},
     generate,
-    source::{Currency, RateType, Source},
+    i18n::{self, Lang, Text},
+    settings::{Key, Settings},
+    source::{Currency, Rate, RateType, Source},
+    table::Format,
     DUNNO,
 };
-use chrono::{DateTime, Utc};
-use std::{env, str::FromStr, sync::Arc};
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use std::{collections::HashMap, env, str::FromStr, sync::Arc, time::Duration};
 use strum::IntoEnumIterator;
 use teloxide::{
     adaptors::{
@@ -15,16 +21,26 @@ use teloxide::{
     },
     prelude::*,
     requests::RequesterExt,
-    types::{InputFile, ParseMode},
+    types::{
+        BotCommand, InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryResult,
+        InlineQueryResultArticle, InputFile, InputMessageContent, InputMessageContentText,
+        ParseMode, Recipient, User,
+    },
     update_listeners::webhooks,
     utils::{
         command::{BotCommands, ParseError},
         html,
     },
+    ApiError, RequestError,
 };
 
 type Bot = DefaultParseMode<Throttle<teloxide::Bot>>;
 const ENV_BOT_TOKEN: &str = "TELOXIDE_TOKEN";
+const HISTORY_DAYS: u32 = 7;
+const MAX_HISTORY_DAYS: u32 = 90;
+const ROUND_QUIET: Duration = Duration::from_secs(5);
+const CHART_DAYS: u32 = 30;
+const MAX_CHART_SRCS: usize = 5;
 
 #[derive(BotCommands, Clone)]
 #[command(
@@ -60,10 +76,68 @@ enum Command {
     RubEurCash,
     #[command(description = "USD/EUR cash ($ - €)")]
     UsdEurCash,
-    #[command(description = "<FROM> <TO>?", parse_with = parse_conv)]
-    Conv { from: Currency, to: Currency },
-    #[command(description = "<FROM> <TO>? cash", parse_with = parse_conv)]
-    ConvCash { from: Currency, to: Currency },
+    #[command(description = "<AMOUNT>? <FROM> <TO>? <text|image>?", parse_with = parse_conv_table, aliases = ["calc"])]
+    Conv {
+        amount: Option<Decimal>,
+        from: Currency,
+        to: Currency,
+        table: Option<TableFormat>,
+    },
+    #[command(description = "<AMOUNT>? <FROM> <TO>? <text|image>? cash", parse_with = parse_conv_table)]
+    ConvCash {
+        amount: Option<Decimal>,
+        from: Currency,
+        to: Currency,
+        table: Option<TableFormat>,
+    },
+    #[command(description = "<AMOUNT>? <FROM> <TO>? across sources", parse_with = parse_conv)]
+    Route {
+        amount: Option<Decimal>,
+        from: Currency,
+        to: Currency,
+    },
+    #[command(description = "<FROM> <TO>? >|< <VALUE> alert", parse_with = parse_alert)]
+    Alert {
+        from: Currency,
+        to: Currency,
+        op: Op,
+        value: Decimal,
+    },
+    #[command(description = "<FROM> <TO>? >|< <VALUE> cash alert", parse_with = parse_alert)]
+    AlertCash {
+        from: Currency,
+        to: Currency,
+        op: Op,
+        value: Decimal,
+    },
+    #[command(description = "list alerts")]
+    Alerts,
+    #[command(description = "<ID> delete alert")]
+    Unalert { id: i64 },
+    #[command(description = "subscribe to the daily digest")]
+    Digest,
+    #[command(description = "unsubscribe from the daily digest")]
+    Undigest,
+    #[command(description = "<KEY>? <VALUE>? user settings", parse_with = parse_settings)]
+    Settings { key: Option<Key>, value: String },
+    #[command(description = "favourite pairs")]
+    Fav,
+    #[command(description = "<SOURCE>|<FROM> <TO>? <DAYS>d? history", parse_with = parse_history)]
+    History {
+        src: Option<Source>,
+        from: Currency,
+        to: Currency,
+        days: u32,
+        rate_type: Option<RateType>,
+    },
+    #[command(description = "<FROM> <TO>? <DAYS>d? <SOURCE>* chart", parse_with = parse_chart)]
+    Chart {
+        from: Currency,
+        to: Currency,
+        days: u32,
+        rate_type: Option<RateType>,
+        srcs: Vec<Source>,
+    },
     #[command(description = "<SOURCE>")]
     Get { src: Source },
     #[command(description = "<SOURCE> cash")]
@@ -74,6 +148,10 @@ enum Command {
     List,
     #[command(description = "bot info")]
     Info,
+    #[command(description = "sources status")]
+    Status,
+    #[command(description = "arbitrage cycles", hide)]
+    Arbitrage,
     #[command(description = "help", aliases = ["h", "?"], hide)]
     Help,
     #[command(description = "welcome", hide)]
@@ -87,6 +165,16 @@ pub async fn run(db: Arc<Database>, cfg: Arc<Config>) -> anyhow::Result<()> {
     unsafe {
         env::remove_var(ENV_BOT_TOKEN);
     }
+    let cfg = match cfg.chart.as_ref().map(chart::load_fonts) {
+        Some(Err(err)) => {
+            log::warn!("chart disabled, load fonts, err: {err}");
+            Arc::new(Config {
+                chart: None,
+                ..(*cfg).clone()
+            })
+        }
+        _ => cfg,
+    };
     bot.set_my_name().name(&cfg.bot.name).await?;
     bot.set_my_short_description()
         .short_description(&cfg.bot.about)
@@ -95,11 +183,25 @@ pub async fn run(db: Arc<Database>, cfg: Arc<Config>) -> anyhow::Result<()> {
         .description(&cfg.bot.description)
         .await?;
     bot.set_my_commands(Command::bot_commands()).await?;
-    let handler = Update::filter_message().branch(
-        dptree::entry()
-            .filter_command::<Command>()
-            .endpoint(command),
-    );
+    for lang in Lang::iter().filter(|v| *v != Lang::En) {
+        bot.set_my_commands(bot_commands(lang))
+            .language_code(lang.to_string())
+            .await?;
+    }
+    tokio::spawn(alert_loop(bot.clone(), db.clone(), cfg.clone()));
+    if cfg.digest.is_some() {
+        tokio::spawn(digest_loop(bot.clone(), db.clone(), cfg.clone()));
+    }
+    let handler = dptree::entry()
+        .branch(
+            Update::filter_message().branch(
+                dptree::entry()
+                    .filter_command::<Command>()
+                    .endpoint(command),
+            ),
+        )
+        .branch(Update::filter_inline_query().endpoint(inline_query))
+        .branch(Update::filter_callback_query().endpoint(callback_query));
     let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
         .dependencies(dptree::deps![db, cfg.clone()])
         .enable_ctrlc_handler()
@@ -138,30 +240,32 @@ async fn command(
     db: Arc<Database>,
     cfg: Arc<Config>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (settings, gen) = user_settings(msg.from.as_ref(), &db, &cfg.gen).await;
+    let gen = &gen;
+    let rate_type = settings.rate_type.unwrap_or(RateType::NoCash);
     match cmd {
         Command::Help => {
-            bot.send_message(
-                msg.chat.id,
-                html::escape(&Command::descriptions().to_string()),
-            )
-            .await?;
+            bot.send_message(msg.chat.id, html::escape(&help_text(gen.lang)))
+                .await?;
         }
         Command::Start(s) => {
-            start_repl(s, bot, msg, db, cfg).await?;
+            start_repl(s, rate_type, bot, msg, db, cfg, gen).await?;
         }
         Command::Usd | Command::UsdCash => {
             conv_repl(
                 &Currency::default(),
                 &Currency::usd(),
+                None,
                 match cmd {
                     Command::UsdCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 false,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
@@ -169,15 +273,17 @@ async fn command(
             conv_repl(
                 &Currency::default(),
                 &Currency::eur(),
+                None,
                 match cmd {
                     Command::EurCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 false,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
@@ -185,15 +291,17 @@ async fn command(
             conv_repl(
                 &Currency::rub(),
                 &Currency::default(),
+                None,
                 match cmd {
                     Command::RubCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 true,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
@@ -201,15 +309,17 @@ async fn command(
             conv_repl(
                 &Currency::default(),
                 &Currency::new("GEL"),
+                None,
                 match cmd {
                     Command::GelCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 false,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
@@ -217,15 +327,17 @@ async fn command(
             conv_repl(
                 &Currency::rub(),
                 &Currency::usd(),
+                None,
                 match cmd {
                     Command::RubUsdCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 false,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
@@ -233,15 +345,17 @@ async fn command(
             conv_repl(
                 &Currency::rub(),
                 &Currency::eur(),
+                None,
                 match cmd {
                     Command::RubEurCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 false,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
@@ -249,39 +363,121 @@ async fn command(
             conv_repl(
                 &Currency::usd(),
                 &Currency::eur(),
+                None,
                 match cmd {
                     Command::UsdEurCash => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 false,
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?
         }
-        Command::Conv { ref from, ref to } | Command::ConvCash { ref from, ref to } => {
+        Command::Conv {
+            amount,
+            ref from,
+            ref to,
+            table,
+        }
+        | Command::ConvCash {
+            amount,
+            ref from,
+            ref to,
+            table,
+        } => {
+            let gen = &config::Gen {
+                table: table.unwrap_or(gen.table),
+                ..gen.clone()
+            };
             conv_repl(
                 from,
                 to,
+                amount,
                 match cmd {
                     Command::ConvCash { .. } => RateType::Cash,
-                    _ => RateType::NoCash,
+                    _ => rate_type,
                 },
                 to == &Currency::default(),
                 bot,
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?;
         }
+        Command::Route {
+            amount,
+            ref from,
+            ref to,
+        } => {
+            route_repl(from, to, amount, rate_type, bot, msg, db, cfg, gen).await?;
+        }
+        Command::Alert {
+            ref from,
+            ref to,
+            op,
+            value,
+        }
+        | Command::AlertCash {
+            ref from,
+            ref to,
+            op,
+            value,
+        } => {
+            let rate_type = match cmd {
+                Command::AlertCash { .. } => RateType::Cash,
+                _ => rate_type,
+            };
+            alert_repl(from, to, op, value, rate_type, bot, msg, db, cfg, gen).await?;
+        }
+        Command::Alerts => {
+            alerts_repl(bot, msg, db).await?;
+        }
+        Command::Unalert { id } => {
+            unalert_repl(id, bot, msg, db, gen).await?;
+        }
+        Command::Digest | Command::Undigest => {
+            digest_repl(matches!(cmd, Command::Digest), bot, msg, db, cfg, gen).await?;
+        }
+        Command::Settings { key, ref value } => {
+            settings_repl(key, value, settings, bot, msg, db).await?;
+        }
+        Command::Fav => {
+            fav_repl(settings, bot, msg, db, cfg, gen).await?;
+        }
+        Command::History {
+            src,
+            from,
+            to,
+            days,
+            rate_type: history_rate_type,
+        } => {
+            let rate_type = match src {
+                Some(Source::Cb) => RateType::Cb,
+                _ => history_rate_type.unwrap_or(rate_type),
+            };
+            history_repl(src, from, to, days, rate_type, bot, msg, db, gen).await?;
+        }
+        Command::Chart {
+            from,
+            to,
+            days,
+            rate_type: chart_rate_type,
+            srcs,
+        } => {
+            let rate_type = chart_rate_type.unwrap_or(rate_type);
+            chart_repl(from, to, days, rate_type, srcs, bot, msg, db, cfg, gen).await?;
+        }
         Command::Get { src } | Command::GetCash { src } | Command::GetCard { src } => {
             src_repl(
                 src,
                 match cmd {
-                    Command::Get { .. } => RateType::NoCash,
+                    Command::Get { .. } => rate_type,
                     Command::GetCash { .. } => RateType::Cash,
                     _ => RateType::Card,
                 },
@@ -289,6 +485,7 @@ async fn command(
                 msg,
                 db,
                 cfg,
+                gen,
             )
             .await?;
         }
@@ -296,61 +493,408 @@ async fn command(
             ls_repl(bot, msg).await?;
         }
         Command::Info => {
-            info_repl(bot, msg, db, cfg).await?;
+            info_repl(bot, msg, db, cfg, gen).await?;
+        }
+        Command::Status => {
+            status_repl(bot, msg, db, cfg, gen).await?;
+        }
+        Command::Arbitrage => {
+            arbitrage_repl(bot, msg, db, cfg, gen).await?;
         }
     }
     Ok(())
 }
 
-fn parse_conv(s: String) -> Result<(Currency, Currency), ParseError> {
+/// A single currency is the AMD table of it, with an amount it is sold for AMD.
+fn parse_conv(s: String) -> Result<(Option<Decimal>, Currency, Currency), ParseError> {
+    let mut s = s.trim();
+    let mut amount = None;
+    if let Some((first, rest)) = s.split_once(char::is_whitespace) {
+        if let Ok(v) = Decimal::from_str(&first.replace([',', '_'], "")) {
+            if v <= Decimal::ZERO {
+                return Err(ParseError::IncorrectFormat(
+                    "amount must be positive".into(),
+                ));
+            }
+            amount = Some(v);
+            s = rest.trim();
+        }
+    }
     if let Some((from, to)) = s.split_once('/') {
-        return Ok((Currency::new(from), Currency::new(to)));
+        return Ok((amount, Currency::new(from), Currency::new(to)));
     }
     let mut ws = s.split_whitespace();
     if let (Some(from), Some(to)) = (ws.next(), ws.next()) {
-        return Ok((Currency::new(from), Currency::new(to)));
+        return Ok((amount, Currency::new(from), Currency::new(to)));
     }
-    Ok((Currency::default(), Currency::new(s)))
+    if amount.is_some() {
+        return Ok((amount, Currency::new(s), Currency::default()));
+    }
+    Ok((amount, Currency::default(), Currency::new(s)))
+}
+
+/// Arguments of `/history` and `/chart`, the sources, the period like `30d`
+/// and the rate type in any order around `<FROM> <TO>?`.
+struct RangeArgs {
+    srcs: Vec<Source>,
+    from: Currency,
+    to: Currency,
+    days: Option<u32>,
+    rate_type: Option<RateType>,
+}
+
+fn parse_range(s: &str) -> Result<RangeArgs, ParseError> {
+    let mut srcs = vec![];
+    let mut days = None;
+    let mut rate_type = None;
+    let mut ws = vec![];
+    for w in s.split_whitespace() {
+        if let Some(v) = w
+            .strip_suffix(['d', 'D'])
+            .and_then(|v| u32::from_str(v).ok())
+        {
+            if v == 0 || v > MAX_HISTORY_DAYS {
+                return Err(ParseError::IncorrectFormat(
+                    format!("days must be 1 to {MAX_HISTORY_DAYS}").into(),
+                ));
+            }
+            days = Some(v);
+        } else if let Ok(v) = RateType::from_str(w) {
+            rate_type = Some(v);
+        } else if let Ok(v) = Source::from_str(w) {
+            srcs.push(v);
+        } else {
+            ws.push(w);
+        }
+    }
+    let (_, from, to) = parse_conv(ws.join(" "))?;
+    if from.is_empty() || to.is_empty() {
+        return Err(ParseError::IncorrectFormat("expected a currency".into()));
+    }
+    Ok(RangeArgs {
+        srcs,
+        from,
+        to,
+        days,
+        rate_type,
+    })
+}
+
+#[allow(clippy::type_complexity)]
+fn parse_history(
+    s: String,
+) -> Result<(Option<Source>, Currency, Currency, u32, Option<RateType>), ParseError> {
+    let args = parse_range(&s)?;
+    if args.srcs.len() > 1 {
+        return Err(ParseError::IncorrectFormat("expected one source".into()));
+    }
+    Ok((
+        args.srcs.first().copied(),
+        args.from,
+        args.to,
+        args.days.unwrap_or(HISTORY_DAYS),
+        args.rate_type,
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+fn parse_chart(
+    s: String,
+) -> Result<(Currency, Currency, u32, Option<RateType>, Vec<Source>), ParseError> {
+    let args = parse_range(&s)?;
+    if args.srcs.len() > MAX_CHART_SRCS {
+        return Err(ParseError::IncorrectFormat(
+            format!("max sources: {MAX_CHART_SRCS}").into(),
+        ));
+    }
+    Ok((
+        args.from,
+        args.to,
+        args.days.unwrap_or(CHART_DAYS),
+        args.rate_type,
+        args.srcs,
+    ))
+}
+
+/// `/conv` arguments with the table format as the last word.
+#[allow(clippy::type_complexity)]
+fn parse_conv_table(
+    s: String,
+) -> Result<(Option<Decimal>, Currency, Currency, Option<TableFormat>), ParseError> {
+    let s = s.trim();
+    let (s, table) = match s.rsplit_once(char::is_whitespace) {
+        Some((rest, last)) => match TableFormat::from_str(last) {
+            Ok(v) => (rest, Some(v)),
+            Err(_) => (s, None),
+        },
+        None => (s, None),
+    };
+    let (amount, from, to) = parse_conv(s.into())?;
+    Ok((amount, from, to, table))
+}
+
+fn parse_alert(s: String) -> Result<(Currency, Currency, Op, Decimal), ParseError> {
+    let Some(idx) = s.find(['>', '<']) else {
+        return Err(ParseError::IncorrectFormat("expected > or <".into()));
+    };
+    let op =
+        Op::from_str(&s[idx..idx + 1]).map_err(|err| ParseError::IncorrectFormat(err.into()))?;
+    let value = Decimal::from_str(&s[idx + 1..].trim().replace([',', '_'], ""))
+        .map_err(|err| ParseError::IncorrectFormat(err.into()))?;
+    if value <= Decimal::ZERO {
+        return Err(ParseError::IncorrectFormat("value must be positive".into()));
+    }
+    let s = s[..idx].trim();
+    let (from, to) = match s.split_once('/') {
+        Some((from, to)) => (Currency::new(from.trim()), Currency::new(to.trim())),
+        None => {
+            let mut ws = s.split_whitespace();
+            let from = Currency::new(ws.next().unwrap_or_default());
+            let to = ws.next().map(Currency::new).unwrap_or_default();
+            (from, to)
+        }
+    };
+    if from.is_empty() || to.is_empty() || from == to {
+        return Err(ParseError::IncorrectFormat("invalid currencies".into()));
+    }
+    Ok((from, to, op, value))
+}
+
+fn parse_settings(s: String) -> Result<(Option<Key>, String), ParseError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok((None, "".into()));
+    }
+    let (key, value) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    let key = Key::from_str(key).map_err(|err| ParseError::IncorrectFormat(err.into()))?;
+    Ok((Some(key), value.trim().into()))
+}
+
+/// Settings of the user and `gen` with them applied.
+async fn user_settings(
+    user: Option<&User>,
+    db: &Database,
+    cfg: &config::Gen,
+) -> (Settings, config::Gen) {
+    let settings = match user {
+        Some(user) => db
+            .get_settings(user.id.0 as i64)
+            .await
+            .unwrap_or_else(|err| {
+                log::error!("get settings: {}, err: {err}", user.id);
+                Settings::default()
+            }),
+        None => Settings::default(),
+    };
+    let lang = settings
+        .lang
+        .or_else(|| user?.language_code.as_deref().and_then(Lang::from_code))
+        .unwrap_or(cfg.lang);
+    let mut gen = settings.apply(cfg);
+    gen.lang = lang;
+    (settings, gen)
+}
+
+/// Bot commands with the descriptions in the language.
+fn bot_commands(lang: Lang) -> Vec<BotCommand> {
+    Command::bot_commands()
+        .into_iter()
+        .map(|mut v| {
+            if let Some(description) = i18n::command_description(lang, &v.command) {
+                v.description = description.into();
+            }
+            v
+        })
+        .collect()
+}
+
+fn help_text(lang: Lang) -> String {
+    let mut lines = vec![Text::Help.tr(lang).to_string(), "".into()];
+    lines.extend(
+        bot_commands(lang)
+            .into_iter()
+            .map(|v| format!("{} — {}", v.command, v.description)),
+    );
+    lines.join("\n")
+}
+
+/// What a `/start` payload or an inline query asks for.
+#[derive(Debug, PartialEq)]
+enum Query {
+    Src(Source, RateType),
+    Conv {
+        amount: Option<Decimal>,
+        from: Currency,
+        to: Currency,
+        rate_type: RateType,
+    },
+}
+
+/// Parses `<SOURCE>` or the `/conv` arguments, with the rate type after `:`
+/// or as the last word, `rate_type` if none.
+fn parse_query(s: &str, mut rate_type: RateType) -> Option<Query> {
+    let mut s = s.trim();
+    if let Some((main, param)) = s.split_once(':') {
+        if let Ok(v) = RateType::from_str(param.trim()) {
+            rate_type = v;
+        }
+        s = main.trim();
+    } else if let Some((main, param)) = s.rsplit_once(char::is_whitespace) {
+        if let Ok(v) = RateType::from_str(param) {
+            rate_type = v;
+            s = main.trim();
+        }
+    }
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(src) = Source::from_str(s) {
+        return Some(Query::Src(src, rate_type));
+    }
+    let (amount, from, to) = parse_conv(s.into()).ok()?;
+    Some(Query::Conv {
+        amount,
+        from,
+        to,
+        rate_type,
+    })
 }
 
 async fn start_repl(
-    mut value: String,
+    value: String,
+    rate_type: RateType,
     bot: Bot,
     msg: Message,
     db: Arc<Database>,
     cfg: Arc<Config>,
+    gen: &config::Gen,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if value.is_empty() {
-        bot.send_message(msg.chat.id, html::escape(&cfg.bot.welcome_msg))
+        let welcome_msg = cfg
+            .bot
+            .welcome_msgs
+            .get(&gen.lang)
+            .unwrap_or(&cfg.bot.welcome_msg);
+        bot.send_message(msg.chat.id, html::escape(welcome_msg))
             .await?;
         return Ok(());
     }
-    let mut rate_type = RateType::NoCash;
-    if let Some((main, param)) = value.split_once(':') {
-        if let Ok(v) = RateType::from_str(param.trim()) {
-            rate_type = v;
+    match parse_query(&value, rate_type) {
+        Some(Query::Src(src, rate_type)) => {
+            src_repl(src, rate_type, bot, msg, db, cfg, gen).await?
         }
-        value = main.into();
-    }
-    if let Ok(src) = Source::from_str(value.trim()) {
-        src_repl(src, rate_type, bot, msg, db, cfg).await?;
-        return Ok(());
+        Some(Query::Conv {
+            amount,
+            from,
+            to,
+            rate_type,
+        }) => {
+            conv_repl(
+                &from,
+                &to,
+                amount,
+                rate_type,
+                to == Currency::default(),
+                bot,
+                msg,
+                db,
+                cfg,
+                gen,
+            )
+            .await?
+        }
+        None => dunno_repl(bot, msg).await?,
     }
-    let (from, to) = parse_conv(value).unwrap();
-    conv_repl(
-        &from,
-        &to,
-        rate_type,
-        to == Currency::default(),
-        bot,
-        msg,
-        db,
-        cfg,
-    )
-    .await?;
     Ok(())
 }
 
+async fn inline_query(
+    bot: Bot,
+    q: InlineQuery,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (settings, gen) = user_settings(Some(&q.from), &db, &cfg.gen).await;
+    let tables = inline_tables(
+        &q.query,
+        settings.rate_type.unwrap_or(RateType::NoCash),
+        &db.get_rates().await,
+        &db.get_status().await,
+        &cfg,
+        &gen,
+    );
+    let results = tables
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (title, s))| {
+            let description = s.lines().next().unwrap_or_default().to_string();
+            let content =
+                InputMessageContentText::new(html::code_block(&s)).parse_mode(ParseMode::Html);
+            InlineQueryResult::Article(
+                InlineQueryResultArticle::new(
+                    idx.to_string(),
+                    title,
+                    InputMessageContent::Text(content),
+                )
+                .description(description),
+            )
+        })
+        .collect::<Vec<_>>();
+    // results follow the user settings, telegram must not share them
+    bot.answer_inline_query(q.id, results)
+        .cache_time(cfg.bot.update_interval as u32)
+        .is_personal(true)
+        .await?;
+    Ok(())
+}
+
+/// Titles and tables of the inline results, empty tables are skipped.
+fn inline_tables(
+    query: &str,
+    rate_type: RateType,
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Vec<(String, String)> {
+    let tables = match parse_query(query, rate_type) {
+        Some(Query::Src(src, rate_type)) => {
+            let s = generate::src_table(src, rates, status, rate_type, gen);
+            vec![(format!("{src} {rate_type:?}"), s)]
+        }
+        Some(Query::Conv {
+            amount: Some(amount),
+            from,
+            to,
+            rate_type,
+        }) => {
+            let s = generate::conv_table(
+                &from,
+                &to,
+                Some(amount),
+                rates,
+                status,
+                rate_type,
+                false,
+                cfg,
+                gen,
+            );
+            vec![(format!("{amount} {from}/{to} {rate_type:?}"), s)]
+        }
+        Some(Query::Conv {
+            amount: None,
+            from,
+            to,
+            rate_type,
+        }) => generate::conv_tables(&from, &to, rates, status, rate_type, cfg, gen)
+            .into_iter()
+            .map(|(from, to, s)| (format!("{from}/{to} {rate_type:?}"), s))
+            .collect(),
+        None => vec![],
+    };
+    tables.into_iter().filter(|(_, s)| !s.is_empty()).collect()
+}
+
 async fn ls_repl(bot: Bot, msg: Message) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let mut srcs = Source::iter()
         .map(|v| v.to_string().to_lowercase())
@@ -365,22 +909,76 @@ async fn info_repl(
     msg: Message,
     db: Arc<Database>,
     cfg: Arc<Config>,
+    gen: &config::Gen,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
     let updated_at = db.get_updated_at().await;
     let update_interval = cfg.bot.update_interval;
-    let lines = [
-        format!("version: {VERSION}"),
-        format!("update_interval: {update_interval}"),
+    let lang = gen.lang;
+    let mut lines = vec![
+        format!("{}: {VERSION}", Text::Version.tr(lang)),
+        format!("{}: {update_interval}", Text::UpdateInterval.tr(lang)),
         format!(
-            "updated_at: {}",
+            "{}: {}",
+            Text::UpdatedAt.tr(lang),
             DateTime::<Utc>::from(updated_at).format("%F %T %Z"),
         ),
     ];
+    let stale = db.get_stale().await;
+    if !stale.is_empty() {
+        lines.push(format!(
+            "{}: {}",
+            Text::Stale.tr(lang),
+            stale
+                .iter()
+                .map(|v| v.to_string().to_lowercase())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ));
+    }
     bot.send_message(msg.chat.id, lines.join("\n")).await?;
     Ok(())
 }
 
+async fn status_repl(
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let status = db.get_status().await;
+    let s = generate::status_table(&status, &cfg, gen);
+    bot.send_message(msg.chat.id, html::code_block(&s)).await?;
+    Ok(())
+}
+
+async fn arbitrage_repl(
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !msg
+        .from
+        .as_ref()
+        .is_some_and(|v| cfg.bot.admins.contains(&v.id.0))
+    {
+        return dunno_repl(bot, msg).await;
+    }
+    let rates = db.get_rates().await;
+    let status = db.get_status().await;
+    let s = generate::arbitrage_table(&rates, &status, RateType::NoCash, &cfg, gen);
+    let s = if s.is_empty() {
+        DUNNO.into()
+    } else {
+        html::code_block(&s)
+    };
+    bot.send_message(msg.chat.id, s).await?;
+    Ok(())
+}
+
 async fn src_repl(
     src: Source,
     rate_type: RateType,
@@ -388,65 +986,813 @@ async fn src_repl(
     msg: Message,
     db: Arc<Database>,
     cfg: Arc<Config>,
+    gen: &config::Gen,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let cached = db.get_cache_src(src, rate_type).await;
-    let s = match cached {
+    view_repl(View::Src { src, rate_type }, &bot, &msg, &db, &cfg, gen).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn conv_repl(
+    mut from: &Currency,
+    mut to: &Currency,
+    amount: Option<Decimal>,
+    rate_type: RateType,
+    inv: bool,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if from.is_empty() || to.is_empty() {
+        return dunno_repl(bot, msg).await;
+    }
+    if amount.is_some() {
+        let view = View::Conv {
+            from: from.clone(),
+            to: to.clone(),
+            amount,
+            rate_type,
+            inv: false,
+        };
+        return view_repl(view, &bot, &msg, &db, &cfg, gen).await;
+    }
+    for idx in 0..2 {
+        let view = View::Conv {
+            from: from.clone(),
+            to: to.clone(),
+            amount: None,
+            rate_type,
+            inv: idx % 2 == inv as usize,
+        };
+        view_repl(view, &bot, &msg, &db, &cfg, gen).await?;
+        std::mem::swap(&mut from, &mut to);
+    }
+    Ok(())
+}
+
+/// Table message whose buttons switch the rate type, swap the direction or
+/// refresh it, the target view is kept in the callback data.
+#[derive(Debug, Clone, PartialEq)]
+enum View {
+    Src {
+        src: Source,
+        rate_type: RateType,
+    },
+    Conv {
+        from: Currency,
+        to: Currency,
+        amount: Option<Decimal>,
+        rate_type: RateType,
+        inv: bool,
+    },
+}
+
+impl View {
+    const RATE_TYPES: [RateType; 4] = [
+        RateType::NoCash,
+        RateType::Cash,
+        RateType::Card,
+        RateType::Online,
+    ];
+    const MAX_DATA_LEN: usize = 64;
+
+    fn rate_type(&self) -> RateType {
+        match self {
+            Self::Src { rate_type, .. } | Self::Conv { rate_type, .. } => *rate_type,
+        }
+    }
+
+    fn with_rate_type(&self, value: RateType) -> Self {
+        let mut view = self.clone();
+        match &mut view {
+            Self::Src { rate_type, .. } | Self::Conv { rate_type, .. } => *rate_type = value,
+        }
+        view
+    }
+
+    fn swapped(&self) -> Option<Self> {
+        match self {
+            Self::Src { .. } => None,
+            Self::Conv {
+                from,
+                to,
+                amount,
+                rate_type,
+                inv,
+            } => Some(Self::Conv {
+                from: to.clone(),
+                to: from.clone(),
+                amount: *amount,
+                rate_type: *rate_type,
+                inv: amount.is_none() && !inv,
+            }),
+        }
+    }
+
+    fn encode(&self) -> String {
+        match self {
+            Self::Src { src, rate_type } => format!("s {src} {}", *rate_type as u8),
+            Self::Conv {
+                from,
+                to,
+                amount,
+                rate_type,
+                inv,
+            } => {
+                let amount = amount.map(|v| v.to_string()).unwrap_or_default();
+                format!("c {from} {to} {} {} {amount}", *rate_type as u8, *inv as u8)
+            }
+        }
+        .trim_end()
+        .into()
+    }
+
+    fn decode(s: &str) -> Option<Self> {
+        let ws = s.split_whitespace().collect::<Vec<_>>();
+        let rate_type = |s: &str| RateType::from_repr(s.parse().ok()?);
+        match ws[..] {
+            ["s", src, rate_type_] => Some(Self::Src {
+                src: Source::from_str(src).ok()?,
+                rate_type: rate_type(rate_type_)?,
+            }),
+            ["c", from, to, rate_type_, inv, ref amount @ ..] if amount.len() <= 1 => {
+                Some(Self::Conv {
+                    from: Currency::new(from),
+                    to: Currency::new(to),
+                    amount: match amount {
+                        [v] => Some(Decimal::from_str(v).ok()?),
+                        _ => None,
+                    },
+                    rate_type: rate_type(rate_type_)?,
+                    inv: inv == "1",
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Buttons, none if a target view does not survive the callback data.
+    fn keyboard(&self) -> Option<InlineKeyboardMarkup> {
+        let button = |text: String, view: Self| {
+            let data = view.encode();
+            (data.len() <= Self::MAX_DATA_LEN && Self::decode(&data).as_ref() == Some(&view))
+                .then(|| InlineKeyboardButton::callback(text, data))
+        };
+        let rate_types = Self::RATE_TYPES
+            .into_iter()
+            .map(|v| {
+                let mut text = format!("{v:?}");
+                if v == self.rate_type() {
+                    text = format!("• {text}");
+                }
+                button(text, self.with_rate_type(v))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut actions = vec![];
+        if let Some(view) = self.swapped() {
+            actions.push(button("⇄".into(), view)?);
+        }
+        actions.push(button("⟳".into(), self.clone())?);
+        Some(InlineKeyboardMarkup::new([rate_types, actions]))
+    }
+
+    async fn text(&self, db: &Database, cfg: &Config, gen: &config::Gen) -> Option<String> {
+        match self {
+            Self::Src { src, rate_type } => src_text(*src, *rate_type, db, gen).await,
+            Self::Conv {
+                from,
+                to,
+                amount,
+                rate_type,
+                inv,
+            } => conv_text(from, to, *amount, *rate_type, *inv, db, cfg, gen).await,
+        }
+    }
+}
+
+async fn view_repl(
+    view: View,
+    bot: &Bot,
+    msg: &Message,
+    db: &Database,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let View::Conv {
+        from,
+        to,
+        amount,
+        rate_type,
+        inv,
+    } = &view
+    {
+        if gen.table == TableFormat::Image {
+            if let Some(png) = conv_image(from, to, *amount, *rate_type, *inv, db, cfg, gen).await {
+                let caption = html::escape(&format!("{from}/{to}"))
+                    + &stale_note(&db.get_stale().await, gen.lang);
+                bot.send_photo(msg.chat.id, InputFile::memory(png).file_name("table.png"))
+                    .caption(caption)
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+    let Some(s) = view.text(db, cfg, gen).await else {
+        bot.send_message(msg.chat.id, DUNNO).await?;
+        return Ok(());
+    };
+    let mut req = bot.send_message(msg.chat.id, s);
+    if let Some(keyboard) = view.keyboard() {
+        req = req.reply_markup(keyboard);
+    }
+    req.await?;
+    Ok(())
+}
+
+async fn callback_query(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (_, gen) = user_settings(Some(&q.from), &db, &cfg.gen).await;
+    let gen = &gen;
+    let view = q.data.as_deref().and_then(View::decode);
+    if let (Some(view), Some(msg)) = (view, q.regular_message()) {
+        let s = view.text(&db, &cfg, gen).await.unwrap_or(DUNNO.into());
+        let mut req = bot.edit_message_text(msg.chat.id, msg.id, s);
+        if let Some(keyboard) = view.keyboard() {
+            req = req.reply_markup(keyboard);
+        }
+        match req.await {
+            Err(RequestError::Api(ApiError::MessageNotModified)) => {}
+            result => {
+                result?;
+            }
+        }
+    }
+    bot.answer_callback_query(q.id).await?;
+    Ok(())
+}
+
+async fn src_text(
+    src: Source,
+    rate_type: RateType,
+    db: &Database,
+    gen: &config::Gen,
+) -> Option<String> {
+    let cached = db.get_cache_src(src, rate_type, gen).await;
+    let mut s = match cached {
         Some(s) => s,
         None => {
             log::debug!("empty cache src");
             let rates = db.get_rates().await;
-            let mut s = generate::src_table(src, &rates, rate_type, &cfg.gen);
-            if !s.is_empty() {
-                s = html::code_inline(&s);
-                db.set_cache_src(src, rate_type, s.clone()).await;
-            } else {
-                s = DUNNO.into()
+            let status = db.get_status().await;
+            let s = generate::src_rates(src, &rates, &status, rate_type)
+                .render(Format::Html, gen.rate_dp);
+            if s.is_empty() {
+                return None;
             }
+            db.set_cache_src(src, rate_type, gen, s.clone()).await;
             s
         }
     };
+    let age = generate::src_age(src, &db.get_status().await, gen);
+    if !age.is_empty() {
+        s.push('\n');
+        s.push_str(&html::italic(&age));
+    }
+    Some(s)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn conv_text(
+    from: &Currency,
+    to: &Currency,
+    amount: Option<Decimal>,
+    rate_type: RateType,
+    inv: bool,
+    db: &Database,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Option<String> {
+    if from.is_empty() || to.is_empty() {
+        return None;
+    }
+    let stale = stale_note(&db.get_stale().await, gen.lang);
+    let cached = match amount {
+        Some(_) => None,
+        None => db.get_cache_conv(from, to, rate_type, inv, gen).await,
+    };
+    let s = match cached {
+        Some(s) => s,
+        None => {
+            log::debug!("empty cache conv");
+            let rates = db.get_rates().await;
+            let status = db.get_status().await;
+            let s =
+                generate::conv_rates(from, to, amount, &rates, &status, rate_type, inv, cfg, gen)
+                    .render(Format::Html, gen.rate_dp, gen.diff_dp);
+            if s.is_empty() {
+                return None;
+            }
+            if amount.is_none() {
+                db.set_cache_conv(from, to, rate_type, inv, gen, s.clone())
+                    .await;
+            }
+            s
+        }
+    };
+    Some(s + &stale)
+}
+
+/// PNG of the conv table, `None` falls back to the text one.
+#[allow(clippy::too_many_arguments)]
+async fn conv_image(
+    from: &Currency,
+    to: &Currency,
+    amount: Option<Decimal>,
+    rate_type: RateType,
+    inv: bool,
+    db: &Database,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Option<Vec<u8>> {
+    cfg.chart.as_ref()?;
+    let rates = db.get_rates().await;
+    let status = db.get_status().await;
+    let table = generate::conv_rates(from, to, amount, &rates, &status, rate_type, inv, cfg, gen);
+    if table.rows.is_empty() {
+        return None;
+    }
+    chart::conv_table(&table, gen.rate_dp, gen.diff_dp)
+        .inspect_err(|err| log::error!("render conv table: {from}/{to}, err: {err}"))
+        .ok()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn route_repl(
+    from: &Currency,
+    to: &Currency,
+    amount: Option<Decimal>,
+    rate_type: RateType,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let rates = db.get_rates().await;
+    let status = db.get_status().await;
+    // the path search over all sources is cpu bound
+    let s = {
+        let (from, to, cfg, gen) = (from.clone(), to.clone(), cfg.clone(), gen.clone());
+        tokio::task::spawn_blocking(move || {
+            generate::route_table(&from, &to, amount, &rates, &status, rate_type, &cfg, &gen)
+        })
+        .await?
+    };
+    let s = if s.is_empty() {
+        DUNNO.into()
+    } else {
+        html::code_block(&s) + &stale_note(&db.get_stale().await, gen.lang)
+    };
     bot.send_message(msg.chat.id, s).await?;
     Ok(())
 }
 
-async fn conv_repl(
-    mut from: &Currency,
-    mut to: &Currency,
+#[allow(clippy::too_many_arguments)]
+async fn alert_repl(
+    from: &Currency,
+    to: &Currency,
+    op: Op,
+    value: Decimal,
     rate_type: RateType,
-    inv: bool,
     bot: Bot,
     msg: Message,
     db: Arc<Database>,
     cfg: Arc<Config>,
+    gen: &config::Gen,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if from.is_empty() || to.is_empty() {
+    if db.get_alerts(Some(msg.chat.id.0)).await?.len() >= cfg.bot.max_alerts {
+        bot.send_message(
+            msg.chat.id,
+            Text::MaxAlerts.fmt(gen.lang, cfg.bot.max_alerts),
+        )
+        .await?;
+        return Ok(());
+    }
+    let id = db
+        .add_alert(msg.chat.id.0, from, to, op, value, rate_type)
+        .await?;
+    let mut s = format!("#{id} {from}/{to} {op} {value} {rate_type:?}");
+    let best = generate::best_rate(
+        from,
+        to,
+        &db.get_rates().await,
+        &db.get_status().await,
+        rate_type,
+        &cfg,
+        gen,
+    );
+    if let Some((src, rate)) = best {
+        s.push_str(&format!(
+            "\n{}: {} {}",
+            Text::Now.tr(gen.lang),
+            rate.round_dp(gen.rate_dp).normalize(),
+            src.to_string().to_lowercase(),
+        ));
+    }
+    bot.send_message(msg.chat.id, html::escape(&s)).await?;
+    Ok(())
+}
+
+async fn alerts_repl(
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let alerts = db.get_alerts(Some(msg.chat.id.0)).await?;
+    if alerts.is_empty() {
         return dunno_repl(bot, msg).await;
     }
+    let s = alerts
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    bot.send_message(msg.chat.id, html::escape(&s)).await?;
+    Ok(())
+}
+
+async fn unalert_repl(
+    id: i64,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !db.delete_alert(msg.chat.id.0, id).await? {
+        return dunno_repl(bot, msg).await;
+    }
+    bot.send_message(msg.chat.id, format!("#{id} {}", Text::Deleted.tr(gen.lang)))
+        .await?;
+    Ok(())
+}
+
+/// Checks the alerts after every collection round and notifies the chats
+/// whose threshold was crossed.
+async fn alert_loop(bot: Bot, db: Arc<Database>, cfg: Arc<Config>) {
+    loop {
+        db.wait_round(ROUND_QUIET).await;
+        if let Err(err) = check_alerts(&bot, &db, &cfg).await {
+            log::error!("check alerts, err: {err}");
+        }
+    }
+}
+
+async fn check_alerts(bot: &Bot, db: &Database, cfg: &Config) -> anyhow::Result<()> {
+    let alerts = db.get_alerts(None).await?;
+    if alerts.is_empty() {
+        return Ok(());
+    }
     let rates = db.get_rates().await;
-    for idx in 0..2 {
-        let is_inv = idx % 2 == inv as usize;
-        let cached = db.get_cache_conv(from, to, rate_type, is_inv).await;
-        let s = match cached {
-            Some(s) => s,
-            None => {
-                log::debug!("empty cache conv");
-                let mut s = generate::conv_table(from, to, &rates, rate_type, is_inv, &cfg.gen);
-                if !s.is_empty() {
-                    s = html::code_block(&s);
-                    db.set_cache_conv(from, to, rate_type, is_inv, s.clone())
-                        .await;
-                } else {
-                    s = DUNNO.into()
+    let status = db.get_status().await;
+    for alert in alerts {
+        let Some((src, rate)) = generate::best_rate(
+            &alert.from,
+            &alert.to,
+            &rates,
+            &status,
+            alert.rate_type,
+            cfg,
+            &cfg.gen,
+        ) else {
+            continue;
+        };
+        let is_met = alert.op.is_met(rate, alert.value);
+        if is_met == alert.triggered {
+            continue;
+        }
+        db.set_alert_triggered(alert.id, is_met).await?;
+        if !is_met {
+            continue;
+        }
+        let gen = config::Gen {
+            lang: chat_lang(db, alert.chat_id, &cfg.gen).await,
+            ..cfg.gen.clone()
+        };
+        let s = generate::alert_note(&alert, src, rate, &gen);
+        if let Err(err) = bot
+            .send_message(ChatId(alert.chat_id), html::escape(&s))
+            .await
+        {
+            log::error!("send alert: {}, err: {err}", alert.id);
+        }
+    }
+    Ok(())
+}
+
+async fn digest_repl(
+    subscribe: bool,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(digest) = &cfg.digest else {
+        return dunno_repl(bot, msg).await;
+    };
+    let s = if subscribe {
+        db.add_digest(msg.chat.id.0).await?;
+        Text::Subscribed.fmt(
+            gen.lang,
+            format!("{} UTC{}", digest.time.format("%H:%M"), digest.utc_offset),
+        )
+    } else if db.delete_digest(msg.chat.id.0).await? {
+        Text::Unsubscribed.tr(gen.lang).into()
+    } else {
+        return dunno_repl(bot, msg).await;
+    };
+    bot.send_message(msg.chat.id, s).await?;
+    Ok(())
+}
+
+/// Sends the digest to the subscribed chats and the channel every day at the
+/// configured time.
+async fn digest_loop(bot: Bot, db: Arc<Database>, cfg: Arc<Config>) {
+    let Some(digest) = &cfg.digest else {
+        return;
+    };
+    loop {
+        let now = Utc::now().with_timezone(&digest.utc_offset);
+        tokio::time::sleep(until_next(now, digest.time)).await;
+        if let Err(err) = send_digest(&bot, &db, &cfg, digest).await {
+            log::error!("send digest, err: {err}");
+        }
+    }
+}
+
+async fn send_digest(
+    bot: &Bot,
+    db: &Database,
+    cfg: &Config,
+    digest: &config::Digest,
+) -> anyhow::Result<()> {
+    let rates = db.get_rates().await;
+    let status = db.get_status().await;
+    let stale = db.get_stale().await;
+    let digest_msgs = |lang| {
+        let gen = config::Gen {
+            lang,
+            ..cfg.gen.clone()
+        };
+        let stale = stale_note(&stale, lang);
+        generate::digest(&digest.pairs, &rates, &status, cfg, &gen)
+            .into_iter()
+            .map(|(title, tables)| {
+                let mut s = html::bold(&title);
+                for table in tables {
+                    s.push('\n');
+                    s.push_str(&html::code_block(&table));
                 }
-                s
-            }
+                s + &stale
+            })
+            .collect::<Vec<_>>()
+    };
+    // the same pairs are empty in every language
+    let mut msgs = HashMap::from([(cfg.gen.lang, digest_msgs(cfg.gen.lang))]);
+    if msgs[&cfg.gen.lang].is_empty() {
+        return Ok(());
+    }
+    let mut recipients = vec![];
+    for id in db.get_digests().await? {
+        recipients.push((Recipient::Id(ChatId(id)), chat_lang(db, id, &cfg.gen).await));
+    }
+    if let Some(channel) = &digest.channel {
+        let recipient = match channel.parse() {
+            Ok(id) => Recipient::Id(ChatId(id)),
+            Err(_) => Recipient::ChannelUsername(channel.clone()),
         };
-        bot.send_message(msg.chat.id, s).await?;
-        std::mem::swap(&mut from, &mut to);
+        recipients.push((recipient, cfg.gen.lang));
+    }
+    for (recipient, lang) in recipients {
+        for s in msgs.entry(lang).or_insert_with(|| digest_msgs(lang)).iter() {
+            if let Err(err) = bot.send_message(recipient.clone(), s).await {
+                log::error!("send digest: {recipient}, err: {err}");
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Language of the chat, private chats share the id of the user.
+async fn chat_lang(db: &Database, chat_id: i64, cfg: &config::Gen) -> Lang {
+    match db.get_settings(chat_id).await {
+        Ok(settings) => settings.lang.unwrap_or(cfg.lang),
+        Err(err) => {
+            log::error!("get settings: {chat_id}, err: {err}");
+            cfg.lang
+        }
+    }
+}
+
+/// Time left until the next `time` of day in the timezone of `now`.
+fn until_next(now: DateTime<FixedOffset>, time: NaiveTime) -> Duration {
+    let now = now.naive_local();
+    let mut next = now.date().and_time(time);
+    if next <= now {
+        next += TimeDelta::days(1);
+    }
+    (next - now).to_std().unwrap_or_default()
+}
+
+async fn settings_repl(
+    key: Option<Key>,
+    value: &str,
+    mut settings: Settings,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(key) = key else {
+        let s = settings.to_lines().join("\n");
+        bot.send_message(msg.chat.id, html::escape(&s)).await?;
+        return Ok(());
+    };
+    let Some(user) = &msg.from else {
+        return dunno_repl(bot, msg).await;
+    };
+    let s = match settings.set(key, value) {
+        Ok(()) => {
+            db.set_settings(user.id.0 as i64, &settings).await?;
+            format!("{key}: {}", settings.get(key))
+        }
+        Err(err) => format!("{key}: {err}"),
+    };
+    bot.send_message(msg.chat.id, html::escape(&s)).await?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn history_repl(
+    src: Option<Source>,
+    from: Currency,
+    to: Currency,
+    days: u32,
+    rate_type: RateType,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let utc_offset = gen.utc_offset;
+    let (mut from, mut to) = (from, to);
+    let mut history = db
+        .get_history(src, &from, &to, rate_type, days, utc_offset)
+        .await?;
+    if history.is_empty() {
+        history = db
+            .get_history(src, &to, &from, rate_type, days, utc_offset)
+            .await?;
+        (from, to) = (to, from);
     }
+    let s = generate::history_table(src, &history, gen);
+    if s.is_empty() {
+        return dunno_repl(bot, msg).await;
+    }
+    let mut title = format!("{from}/{to} {days}d");
+    if let Some(src) = src {
+        title = format!("{} {title}", src.to_string().to_lowercase());
+    }
+    bot.send_message(
+        msg.chat.id,
+        html::bold(&title) + "\n" + &html::code_block(&s),
+    )
+    .await?;
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
+async fn chart_repl(
+    from: Currency,
+    to: Currency,
+    days: u32,
+    rate_type: RateType,
+    srcs: Vec<Source>,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(chart_cfg) = &cfg.chart else {
+        return dunno_repl(bot, msg).await;
+    };
+    let utc_offset = gen.utc_offset;
+    let (mut from, mut to, mut sell) = (from, to, false);
+    let mut rates = db
+        .get_pair_rates(None, &from, &to, rate_type, days, utc_offset)
+        .await?;
+    if rates.is_empty() {
+        rates = db
+            .get_pair_rates(None, &to, &from, rate_type, days, utc_offset)
+            .await?;
+        (from, to, sell) = (to, from, true);
+    }
+    let shown = rates
+        .iter()
+        .filter(|v| v.0 != Source::Cb && gen.show.contains(v.0.prefix()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut series = vec![chart::Series {
+        name: "best".into(),
+        points: chart::best_series(&shown, sell, gen.max_age),
+        reference: false,
+    }];
+    for src in srcs {
+        series.push(chart::Series {
+            name: src.to_string().to_lowercase(),
+            points: chart::src_series(&rates, src, sell),
+            reference: false,
+        });
+    }
+    let cb = db
+        .get_pair_rates(Some(Source::Cb), &from, &to, RateType::Cb, days, utc_offset)
+        .await?;
+    series.push(chart::Series {
+        name: Source::Cb.to_string().to_lowercase(),
+        points: chart::src_series(&cb, Source::Cb, false),
+        reference: true,
+    });
+    series.retain(|v| !v.points.is_empty());
+    if series.is_empty() {
+        return dunno_repl(bot, msg).await;
+    }
+    let side = if sell { "sell" } else { "buy" };
+    let title = format!("{from}/{to} {side} {days}d");
+    let png = match chart::render(&title, &series, utc_offset, chart_cfg) {
+        Ok(v) => v,
+        Err(err) => {
+            log::error!("render chart: {title}, err: {err}");
+            return dunno_repl(bot, msg).await;
+        }
+    };
+    bot.send_photo(msg.chat.id, InputFile::memory(png).file_name("chart.png"))
+        .caption(html::escape(&title))
+        .await?;
+    Ok(())
+}
+
+async fn fav_repl(
+    settings: Settings,
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    gen: &config::Gen,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if settings.pairs.is_empty() {
+        bot.send_message(msg.chat.id, Text::NoPairs.tr(gen.lang))
+            .await?;
+        return Ok(());
+    }
+    let rate_type = settings.rate_type.unwrap_or(RateType::NoCash);
+    for (from, to) in &settings.pairs {
+        conv_repl(
+            from,
+            to,
+            None,
+            rate_type,
+            to == &Currency::default(),
+            bot.clone(),
+            msg.clone(),
+            db.clone(),
+            cfg.clone(),
+            gen,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+fn stale_note(stale: &[Source], lang: Lang) -> String {
+    if stale.is_empty() {
+        return "".into();
+    }
+    let srcs = stale
+        .iter()
+        .map(|v| v.to_string().to_lowercase())
+        .collect::<Vec<_>>();
+    format!(
+        "\n{}",
+        html::italic(&format!("{}: {}", Text::Stale.tr(lang), srcs.join(", ")))
+    )
+}
+
 async fn dunno_repl(
     bot: Bot,
     msg: Message,
@@ -454,3 +1800,288 @@ async fn dunno_repl(
     bot.send_message(msg.chat.id, DUNNO).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::sync::LazyLock;
+
+    static CFG: LazyLock<Config> =
+        LazyLock::new(|| toml::from_str(include_str!("../config/config.toml")).unwrap());
+
+    #[test]
+    fn test_parse_history() {
+        assert_eq!(
+            parse_history("RUB AMD 30d".into()).ok(),
+            Some((None, Currency::rub(), Currency::default(), 30, None))
+        );
+        assert_eq!(
+            parse_history("acba usd cash".into()).ok(),
+            Some((
+                Some(Source::Acba),
+                Currency::default(),
+                Currency::usd(),
+                HISTORY_DAYS,
+                Some(RateType::Cash)
+            ))
+        );
+        assert!(parse_history("".into()).is_err());
+        assert!(parse_history("usd 0d".into()).is_err());
+        assert!(parse_history("usd 365d".into()).is_err());
+    }
+
+    #[test]
+    fn test_parse_conv_table() {
+        assert_eq!(
+            parse_conv_table("100 rub usd img".into()).ok(),
+            Some((
+                Some(Decimal::ONE_HUNDRED),
+                Currency::rub(),
+                Currency::usd(),
+                Some(TableFormat::Image)
+            ))
+        );
+        assert_eq!(
+            parse_conv_table("rub usd".into()).ok(),
+            Some((None, Currency::rub(), Currency::usd(), None))
+        );
+    }
+
+    #[test]
+    fn test_parse_chart() {
+        assert_eq!(
+            parse_chart("RUB AMD 30d cash acba".into()).ok(),
+            Some((
+                Currency::rub(),
+                Currency::default(),
+                30,
+                Some(RateType::Cash),
+                vec![Source::Acba]
+            ))
+        );
+        assert_eq!(
+            parse_chart("usd".into()).ok(),
+            Some((
+                Currency::default(),
+                Currency::usd(),
+                CHART_DAYS,
+                None,
+                vec![]
+            ))
+        );
+        assert!(parse_chart("usd acba ameria ardshin evoca ineco vtb".into()).is_err());
+        assert!(parse_history("usd acba ameria".into()).is_err());
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        let parse = |s: &str| parse_settings(s.into()).expect("panic");
+        assert_eq!(parse(""), (None, "".into()));
+        assert_eq!(
+            parse("rate_type cash"),
+            (Some(Key::RateType), "cash".into())
+        );
+        assert_eq!(
+            parse("pairs RUB/USD, USD"),
+            (Some(Key::Pairs), "RUB/USD, USD".into())
+        );
+        assert_eq!(parse("show"), (Some(Key::Show), "".into()));
+        assert!(parse_settings("color red".into()).is_err());
+    }
+
+    #[test]
+    fn test_view() {
+        let view = View::Conv {
+            from: Currency::rub(),
+            to: Currency::usd(),
+            amount: Some(dec!(1000.5)),
+            rate_type: RateType::Cash,
+            inv: false,
+        };
+        assert_eq!(view.encode(), "c RUB USD 1 0 1000.5");
+        assert_eq!(View::decode(&view.encode()), Some(view.clone()));
+        let swapped = view.swapped().expect("panic");
+        assert_eq!(swapped.encode(), "c USD RUB 1 0 1000.5");
+        let view = View::Conv {
+            from: Currency::rub(),
+            to: Currency::usd(),
+            amount: None,
+            rate_type: RateType::Cash,
+            inv: true,
+        };
+        assert_eq!(view.encode(), "c RUB USD 1 1");
+        assert_eq!(View::decode(&view.encode()), Some(view.clone()));
+        assert_eq!(view.swapped().expect("panic").encode(), "c USD RUB 1 0");
+        let keyboard = view.keyboard().expect("panic");
+        assert_eq!(keyboard.inline_keyboard[0].len(), 4);
+        assert_eq!(keyboard.inline_keyboard[0][1].text, "• Cash");
+        assert_eq!(keyboard.inline_keyboard[1].len(), 2);
+        let view = View::Src {
+            src: Source::Acba,
+            rate_type: RateType::NoCash,
+        };
+        assert_eq!(View::decode(&view.encode()), Some(view.clone()));
+        assert_eq!(view.keyboard().expect("panic").inline_keyboard[1].len(), 1);
+        let view = View::Conv {
+            from: Currency::new("A B"),
+            to: Currency::usd(),
+            amount: None,
+            rate_type: RateType::NoCash,
+            inv: false,
+        };
+        assert!(view.keyboard().is_none());
+        assert_eq!(View::decode("x"), None);
+        assert_eq!(View::decode("s acba 9"), None);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("acba", RateType::NoCash),
+            Some(Query::Src(Source::Acba, RateType::NoCash))
+        );
+        assert_eq!(
+            parse_query("acba:cash", RateType::NoCash),
+            Some(Query::Src(Source::Acba, RateType::Cash))
+        );
+        assert_eq!(
+            parse_query("rub usd cash", RateType::NoCash),
+            Some(Query::Conv {
+                amount: None,
+                from: Currency::rub(),
+                to: Currency::usd(),
+                rate_type: RateType::Cash,
+            })
+        );
+        assert_eq!(
+            parse_query("100 usd", RateType::Card),
+            Some(Query::Conv {
+                amount: Some(dec!(100)),
+                from: Currency::usd(),
+                to: Currency::default(),
+                rate_type: RateType::Card,
+            })
+        );
+        assert_eq!(parse_query(" ", RateType::NoCash), None);
+        assert_eq!(parse_query("0 usd", RateType::NoCash), None);
+    }
+
+    #[test]
+    fn test_until_next() {
+        let offset = FixedOffset::east_opt(4 * 3600).expect("panic");
+        let time = NaiveTime::from_hms_opt(9, 0, 0).expect("panic");
+        let now = DateTime::parse_from_rfc3339("2024-05-01T08:30:00+04:00").expect("panic");
+        assert_eq!(until_next(now, time), Duration::from_secs(30 * 60));
+        let now = DateTime::parse_from_rfc3339("2024-05-01T05:00:00Z")
+            .expect("panic")
+            .with_timezone(&offset);
+        assert_eq!(until_next(now, time), Duration::from_secs(24 * 3600));
+    }
+
+    #[test]
+    fn test_parse_alert() {
+        let parse = |s: &str| parse_alert(s.into()).expect("panic");
+        assert_eq!(
+            parse("RUB AMD > 4.9"),
+            (Currency::rub(), Currency::default(), Op::Above, dec!(4.9))
+        );
+        assert_eq!(
+            parse("usd<380"),
+            (Currency::usd(), Currency::default(), Op::Below, dec!(380))
+        );
+        assert_eq!(
+            parse("usd/rub > 90"),
+            (Currency::usd(), Currency::rub(), Op::Above, dec!(90))
+        );
+        assert!(parse_alert("rub 4.9".into()).is_err());
+        assert!(parse_alert("rub > x".into()).is_err());
+        assert!(parse_alert("rub > 0".into()).is_err());
+        assert!(parse_alert("> 4.9".into()).is_err());
+        assert!(parse_alert("amd > 4.9".into()).is_err());
+    }
+
+    #[test]
+    fn test_bot_commands() {
+        let commands = bot_commands(Lang::Ru);
+        assert_eq!(commands.len(), Command::bot_commands().len());
+        assert!(commands
+            .iter()
+            .any(|v| v.command == "/list" && v.description == "список источников"));
+        assert!(help_text(Lang::Hy).starts_with(Text::Help.tr(Lang::Hy)));
+    }
+
+    #[test]
+    fn test_parse_conv() {
+        let parse = |s: &str| parse_conv(s.into()).expect("panic");
+        assert_eq!(parse("usd"), (None, Currency::default(), Currency::usd()));
+        assert_eq!(parse("rub usd"), (None, Currency::rub(), Currency::usd()));
+        assert_eq!(
+            parse("150000 RUB USD"),
+            (Some(dec!(150000)), Currency::rub(), Currency::usd())
+        );
+        assert_eq!(
+            parse("150,000 rub/usd"),
+            (Some(dec!(150000)), Currency::rub(), Currency::usd())
+        );
+        assert_eq!(
+            parse("100.5 usd"),
+            (Some(dec!(100.5)), Currency::usd(), Currency::default())
+        );
+        assert!(parse_conv("-5 usd".into()).is_err());
+    }
+
+    #[test]
+    fn test_inline_tables() {
+        let rate = |rate_type, buy, sell| Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let rates = HashMap::from([(
+            Source::Acba,
+            vec![
+                rate(RateType::NoCash, dec!(387.456), dec!(392.1)),
+                rate(RateType::Cash, dec!(386), dec!(393)),
+            ],
+        )]);
+        let tables = |query, settings: &Settings| {
+            let rate_type = settings.rate_type.unwrap_or(RateType::NoCash);
+            let gen = settings.apply(&CFG.gen);
+            inline_tables(query, rate_type, &rates, &HashMap::new(), &CFG, &gen)
+        };
+        let user = Settings::default();
+        let other = Settings {
+            rate_type: Some(RateType::Cash),
+            rate_dp: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            tables("acba", &user),
+            vec![("Acba NoCash".into(), "387.456 | 392.1 | USD/AMD\n".into())]
+        );
+        let rounded = Settings {
+            rate_dp: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            tables("acba", &rounded),
+            vec![("Acba NoCash".into(), "387.5 | 392.1 | USD/AMD\n".into())]
+        );
+        assert_eq!(
+            tables("acba", &other),
+            vec![("Acba Cash".into(), "386 | 393 | USD/AMD\n".into())]
+        );
+        let titles = |settings| {
+            tables("usd", settings)
+                .into_iter()
+                .map(|v| v.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(&user), ["AMD/USD NoCash", "USD/AMD NoCash"]);
+        assert_eq!(titles(&other), ["AMD/USD Cash", "USD/AMD Cash"]);
+        assert!(tables("", &user).is_empty());
+    }
+}
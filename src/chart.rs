@@ -0,0 +1,393 @@
+//! Line charts and tables of rates rendered to PNG.
+
+use crate::{
+    config,
+    source::{Rate, Source},
+    table::{self, ConvTable},
+};
+use anyhow::{anyhow, ensure};
+use chrono::{DateTime, FixedOffset};
+use plotters::{prelude::*, style::register_font};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::{
+    collections::HashMap,
+    fs,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const FONT: &str = "sans-serif";
+const MONO_FONT: &str = "monospace";
+const TABLE_FONT_SIZE: u32 = 16;
+const PADDING: i32 = 12;
+
+/// A line of the chart, time in unix seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(i64, Decimal)>,
+    /// Drawn dashed, like the central bank rate.
+    pub reference: bool,
+}
+
+/// Registers the fonts of the chart and table texts, once loaded the later
+/// calls keep them.
+pub fn load_fonts(cfg: &config::Chart) -> anyhow::Result<()> {
+    // the registered fonts live for the whole process
+    static LOADED: Mutex<bool> = Mutex::new(false);
+    let mut loaded = LOADED.lock().unwrap();
+    if *loaded {
+        return Ok(());
+    }
+    let fonts = [(FONT, &cfg.font), (MONO_FONT, &cfg.mono_font)]
+        .into_iter()
+        .map(|(name, path)| Ok((name, path, fs::read(path)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for (name, path, bytes) in fonts {
+        register_font(name, FontStyle::Normal, Box::leak(bytes.into_boxed_slice()))
+            .map_err(|_| anyhow!("invalid font: {path}"))?;
+    }
+    *loaded = true;
+    Ok(())
+}
+
+/// Buy rates are best when highest, sell rates when lowest.
+fn get_value(rate: &Rate, sell: bool) -> Option<Decimal> {
+    if sell {
+        rate.sell
+    } else {
+        rate.buy
+    }
+}
+
+fn to_unix(ts: SystemTime) -> i64 {
+    ts.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
+
+/// Best rate across sources at every update, the last rate of a source
+/// counts for `max_age` seconds.
+pub fn best_series(
+    rates: &[(Source, SystemTime, Rate)],
+    sell: bool,
+    max_age: u64,
+) -> Vec<(i64, Decimal)> {
+    let max_age = i64::try_from(max_age).unwrap_or(i64::MAX);
+    let mut last: HashMap<Source, (i64, Decimal)> = HashMap::new();
+    let mut series: Vec<(i64, Decimal)> = vec![];
+    for (src, ts, rate) in rates {
+        let Some(value) = get_value(rate, sell) else {
+            continue;
+        };
+        let ts = to_unix(*ts);
+        last.insert(*src, (ts, value));
+        let values = last.values().filter(|v| ts - v.0 <= max_age).map(|v| v.1);
+        let best = if sell { values.min() } else { values.max() };
+        let Some(best) = best else {
+            continue;
+        };
+        match series.last_mut() {
+            Some(v) if v.0 == ts => v.1 = best,
+            _ => series.push((ts, best)),
+        }
+    }
+    series
+}
+
+pub fn src_series(
+    rates: &[(Source, SystemTime, Rate)],
+    src: Source,
+    sell: bool,
+) -> Vec<(i64, Decimal)> {
+    rates
+        .iter()
+        .filter(|v| v.0 == src)
+        .filter_map(|(_, ts, rate)| Some((to_unix(*ts), get_value(rate, sell)?)))
+        .collect()
+}
+
+/// PNG of the series with the dates at `utc_offset`.
+pub fn render(
+    title: &str,
+    series: &[Series],
+    utc_offset: FixedOffset,
+    cfg: &config::Chart,
+) -> anyhow::Result<Vec<u8>> {
+    let points = series
+        .iter()
+        .flat_map(|v| &v.points)
+        .map(|(x, y)| (*x, y.to_f64().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    ensure!(!points.is_empty(), "no points");
+    let min_x = points.iter().map(|v| v.0).min().unwrap_or_default();
+    let max_x = points.iter().map(|v| v.0).max().unwrap_or_default();
+    let min_y = points.iter().map(|v| v.1).fold(f64::INFINITY, f64::min);
+    let max_y = points.iter().map(|v| v.1).fold(f64::NEG_INFINITY, f64::max);
+    let pad = ((max_y - min_y) * 0.05).max(max_y.abs() * 0.001);
+    let (width, height) = (cfg.width, cfg.height);
+    let mut buf = vec![0; width as usize * height as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut buf, (width, height)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (FONT, 20))
+            .margin(12)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d(min_x..max_x.max(min_x + 1), min_y - pad..max_y + pad)?;
+        let x_format = if max_x - min_x < 3 * 86400 {
+            "%m-%d %H:%M"
+        } else {
+            "%m-%d"
+        };
+        let x_label = |v: &i64| {
+            DateTime::from_timestamp(*v, 0)
+                .map(|v| v.with_timezone(&utc_offset).format(x_format).to_string())
+                .unwrap_or_default()
+        };
+        chart
+            .configure_mesh()
+            .x_labels(6)
+            .x_label_formatter(&x_label)
+            .label_style((FONT, 12))
+            .draw()?;
+        for (i, s) in series.iter().enumerate() {
+            let points = s
+                .points
+                .iter()
+                .map(|(x, y)| (*x, y.to_f64().unwrap_or_default()));
+            let color = if s.reference {
+                BLACK.mix(0.5)
+            } else {
+                Palette99::pick(i).to_rgba()
+            };
+            let style = color.stroke_width(2);
+            let anno = if s.reference {
+                chart.draw_series(DashedLineSeries::new(points, 6, 4, style))?
+            } else {
+                chart.draw_series(LineSeries::new(points, style))?
+            };
+            anno.label(&s.name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+        }
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .label_font((FONT, 12))
+            .draw()?;
+        root.present()?;
+    }
+    encode(&buf, width, height)
+}
+
+/// PNG of the `conv_table` rows, the best bank rate green and negative
+/// diffs red.
+pub fn conv_table(table: &ConvTable, rate_dp: u32, diff_dp: u32) -> anyhow::Result<Vec<u8>> {
+    let rows = &table.rows;
+    ensure!(!rows.is_empty(), "no rows");
+    let cells = rows
+        .iter()
+        .zip(table.cells(rate_dp, diff_dp))
+        .map(|(v, (value, diff))| {
+            [
+                format!("{} {}", v.src.prefix(), v.src),
+                value,
+                diff,
+                table::path_to_string(&v.path),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..4)
+        .map(|i| {
+            cells
+                .iter()
+                .map(|v| v[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let style = (MONO_FONT, TABLE_FONT_SIZE).into_font();
+    let (char_width, _) = style.box_size("0")?;
+    let line_height = TABLE_FONT_SIZE * 3 / 2;
+    let chars = widths.iter().sum::<usize>() + " | ".len() * (widths.len() - 1);
+    let width = chars as u32 * char_width + PADDING as u32 * 2;
+    let height = rows.len() as u32 * line_height + PADDING as u32 * 2;
+    let best = rows.iter().find(|v| v.src.is_bank()).map(|v| v.rate);
+    let mut buf = vec![0; width as usize * height as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut buf, (width, height)).into_drawing_area();
+        root.fill(&WHITE)?;
+        for (i, (row, cells)) in rows.iter().zip(&cells).enumerate() {
+            let y = PADDING + (i as u32 * line_height) as i32;
+            if i % 2 == 1 {
+                root.draw(&Rectangle::new(
+                    [(0, y), (width as i32, y + line_height as i32)],
+                    RGBColor(0xf0, 0xf0, 0xf0).filled(),
+                ))?;
+            }
+            let y = y + ((line_height - TABLE_FONT_SIZE) / 2) as i32;
+            let mut x = PADDING;
+            for (j, cell) in cells.iter().enumerate() {
+                let color = match j {
+                    1 if Some(row.rate) == best => RGBColor(0x1b, 0x8a, 0x3c),
+                    2 if row
+                        .diff
+                        .is_some_and(|v| v.is_sign_negative() && !v.is_zero()) =>
+                    {
+                        RGBColor(0xc6, 0x28, 0x28)
+                    }
+                    _ => BLACK,
+                };
+                // diffs are right aligned like in the text table
+                let pad = if j == 2 {
+                    widths[j] - cell.chars().count()
+                } else {
+                    0
+                };
+                root.draw_text(
+                    cell,
+                    &style.color(&color),
+                    (x + (pad as u32 * char_width) as i32, y),
+                )?;
+                x += ((widths[j] + " | ".len()) as u32 * char_width) as i32;
+                if j + 1 < cells.len() {
+                    let sep_x = x - (2 * char_width) as i32;
+                    root.draw_text("|", &style.color(&BLACK.mix(0.4)), (sep_x, y))?;
+                }
+            }
+        }
+        root.present()?;
+    }
+    encode(&buf, width, height)
+}
+
+fn encode(buf: &[u8], width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+    let mut png = vec![];
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(buf)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        source::{Currency, RateType},
+        table::ConvRow,
+    };
+    use rust_decimal_macros::dec;
+    use std::{path::Path, sync::LazyLock, time::Duration};
+
+    #[test]
+    fn test_best_series() {
+        let ts = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let rate = |buy, sell| Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell,
+        };
+        let rates = [
+            (Source::Acba, ts(0), rate(dec!(386), Some(dec!(391)))),
+            (Source::Ameria, ts(0), rate(dec!(387), None)),
+            (Source::Acba, ts(300), rate(dec!(385), Some(dec!(390)))),
+            (Source::Acba, ts(1000), rate(dec!(384), Some(dec!(389)))),
+        ];
+        assert_eq!(
+            best_series(&rates, false, 600),
+            vec![(0, dec!(387)), (300, dec!(387)), (1000, dec!(384))]
+        );
+        assert_eq!(
+            best_series(&rates, true, 600),
+            vec![(0, dec!(391)), (300, dec!(390)), (1000, dec!(389))]
+        );
+        assert_eq!(
+            src_series(&rates, Source::Ameria, false),
+            vec![(0, dec!(387))]
+        );
+    }
+
+    static CFG: LazyLock<Config> =
+        LazyLock::new(|| toml::from_str(include_str!("../config/config.toml")).unwrap());
+
+    /// The fonts of the alpine image from the config or of debian, `None`
+    /// when the host has neither and the test is skipped.
+    fn load_test_fonts() -> anyhow::Result<Option<config::Chart>> {
+        let alpine = CFG.chart.clone().expect("panic");
+        let debian = config::Chart {
+            font: "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".into(),
+            mono_font: "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf".into(),
+            ..alpine.clone()
+        };
+        let Some(chart) = [alpine, debian]
+            .into_iter()
+            .find(|v| Path::new(&v.font).exists() && Path::new(&v.mono_font).exists())
+        else {
+            eprintln!("skipped, no dejavu fonts");
+            return Ok(None);
+        };
+        load_fonts(&chart)?;
+        Ok(Some(chart))
+    }
+
+    #[test]
+    fn test_render() -> anyhow::Result<()> {
+        let Some(chart) = load_test_fonts()? else {
+            return Ok(());
+        };
+        let series = [
+            Series {
+                name: "best".into(),
+                points: vec![(0, dec!(386)), (86400, dec!(387)), (172800, dec!(385))],
+                reference: false,
+            },
+            Series {
+                name: "cb".into(),
+                points: vec![(0, dec!(386.5)), (172800, dec!(386.2))],
+                reference: true,
+            },
+        ];
+        let png = render("USD/AMD buy 2d", &series, CFG.gen.utc_offset, &chart)?;
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(render("", &[], CFG.gen.utc_offset, &chart).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_conv_table() -> anyhow::Result<()> {
+        if load_test_fonts()?.is_none() {
+            return Ok(());
+        }
+        let row = |src, rate, diff| ConvRow {
+            src,
+            rate_type: RateType::NoCash,
+            rate,
+            diff: Some(diff),
+            received: Decimal::ZERO,
+            path: vec![Currency::rub(), Currency::default(), Currency::usd()],
+            updated_at: None,
+        };
+        let mut table = ConvTable {
+            from: Currency::rub(),
+            to: Currency::usd(),
+            amount: None,
+            rate_type: RateType::NoCash,
+            inv: false,
+            rows: vec![
+                row(Source::Acba, dec!(90), dec!(0)),
+                row(Source::Ameria, dec!(91.5), dec!(-1.64)),
+            ],
+        };
+        let png = conv_table(&table, CFG.gen.rate_dp, CFG.gen.diff_dp)?;
+        assert!(png.starts_with(b"\x89PNG"));
+        table.rows.clear();
+        assert!(conv_table(&table, CFG.gen.rate_dp, CFG.gen.diff_dp).is_err());
+        Ok(())
+    }
+}
@@ -1,59 +1,589 @@
 #[cfg(feature = "moex")]
 use crate::source::moex;
 use crate::{
-    config::Config,
+    config::{self, Config},
+    database::{Database, Status},
     graph,
-    source::{self, Rate, RateType, Source},
+    source::{self, Currency, Error, Rate, RateType, Source},
 };
+use anyhow::{anyhow, bail};
 use rust_decimal::Decimal;
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 #[cfg(feature = "moex")]
 use std::{env, sync::LazyLock};
 use strum::IntoEnumIterator;
-use tokio::sync::mpsc;
+use tokio::{sync::oneshot, task::JoinSet, time::Instant};
 
-pub async fn collect(
-    client: &reqwest::Client,
-    cfg: Arc<Config>,
-    tx: mpsc::Sender<(Source, Vec<Rate>)>,
-) {
+#[derive(Debug)]
+pub struct Report {
+    pub src: Source,
+    pub latency: Duration,
+    pub result: anyhow::Result<Vec<Rate>>,
+    /// Rates dropped by the sanity checks.
+    pub rejected: usize,
+}
+
+/// Enabled sources grouped by the parent source they are fetched with.
+fn groups(cfg: &Config) -> BTreeMap<Source, Vec<Source>> {
     #[cfg(feature = "moex")]
     static MOEX_OK: LazyLock<bool> = LazyLock::new(|| {
         !env::var(moex::ENV_TINKOFF_TOKEN)
             .unwrap_or_default()
             .is_empty()
     });
+    let mut groups: BTreeMap<Source, Vec<Source>> = BTreeMap::new();
     for src in Source::iter().filter(|v| cfg.src.is_enabled_for(*v)) {
         #[cfg(feature = "moex")]
-        if src == Source::MOEX && !MOEX_OK.clone() {
+        if src == Source::MOEX && !*MOEX_OK {
             continue;
         }
-        let client = client.clone();
-        let cfg = cfg.clone();
-        let tx = tx.clone();
-        tokio::spawn(async move {
-            let result = source::collect(&client, &cfg.src, src).await;
-            match result {
-                Ok(rates) => {
-                    let rates = rates
-                        .iter()
-                        .filter(|v| {
-                            (!v.from.is_empty() && !v.to.is_empty())
-                                && (v.buy.is_some_and(|v| v > Decimal::ZERO)
-                                    || v.sell.is_some_and(|v| v > Decimal::ZERO))
-                        })
-                        .cloned()
-                        .collect::<Vec<_>>();
-                    if rates.is_empty() {
-                        return;
-                    }
-                    if graph::detect_arbitrage(&rates, RateType::NoCash) {
-                        log::info!("arbitrage detected: {src}");
+        groups
+            .entry(src.parent().unwrap_or(src))
+            .or_default()
+            .push(src);
+    }
+    groups
+}
+
+/// Sources of a group that are not paused by the circuit breaker and the
+/// number of retries they are allowed.
+fn active(
+    srcs: &[Source],
+    status: &HashMap<Source, Status>,
+    cfg: &config::Bot,
+) -> (Vec<Source>, u32) {
+    let srcs = srcs
+        .iter()
+        .filter(|v| {
+            let is_paused = status.get(v).is_some_and(|v| is_paused(v, cfg));
+            if is_paused {
+                log::debug!("paused: {v}");
+            }
+            !is_paused
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    let retries = if srcs.iter().any(|v| {
+        status
+            .get(v)
+            .is_some_and(|v| v.failures >= cfg.breaker_threshold)
+    }) {
+        0
+    } else {
+        cfg.retries
+    };
+    (srcs, retries)
+}
+
+/// Collects every group of sources on its own schedule until ctrl+c.
+pub async fn run(db: Arc<Database>, cfg: Arc<Config>) -> anyhow::Result<()> {
+    let mut tasks = spawn_groups(db.clone(), cfg.clone()).await;
+    tokio::spawn(prune_loop(db.clone(), cfg.clone()));
+    tokio::spawn(arbitrage_loop(db, cfg));
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            log::debug!("ctrl+c");
+        }
+        Some(result) = tasks.join_next() => {
+            result??;
+        }
+    }
+    Ok(())
+}
+
+/// Spawns a task per group, the central bank group first and the others after
+/// its first round, as their rates are checked against it. The others do not
+/// wait longer than a request timeout of the central bank.
+async fn spawn_groups(db: Arc<Database>, cfg: Arc<Config>) -> JoinSet<anyhow::Result<()>> {
+    let mut groups = groups(&cfg);
+    let mut tasks = JoinSet::new();
+    if let Some(srcs) = groups.remove(&Source::Cb) {
+        let (tx, rx) = oneshot::channel();
+        tasks.spawn(run_group(
+            db.clone(),
+            cfg.clone(),
+            Source::Cb,
+            srcs,
+            Some(tx),
+        ));
+        let timeout = cfg
+            .src
+            .options_for(Source::Cb)
+            .timeout
+            .unwrap_or(cfg.bot.reqwest_timeout);
+        // the sender is dropped if the group fails to start
+        if tokio::time::timeout(Duration::from_secs(timeout), rx)
+            .await
+            .is_err()
+        {
+            log::warn!("cb round timed out, not checking against it");
+        }
+    }
+    for (parent, srcs) in groups {
+        tasks.spawn(run_group(db.clone(), cfg.clone(), parent, srcs, None));
+    }
+    tasks
+}
+
+/// Runs every group until each source was attempted once.
+#[cfg(test)]
+pub(crate) async fn run_once(cfg: Config) -> anyhow::Result<Arc<Database>> {
+    let expected = groups(&cfg).into_values().flatten().collect::<Vec<_>>();
+    let db = Database::new(crate::store::Store::open_in_memory()?);
+    let mut tasks = spawn_groups(db.clone(), Arc::new(cfg)).await;
+    loop {
+        if let Some(result) = tasks.try_join_next() {
+            result??;
+        }
+        let status = db.get_status().await;
+        if expected
+            .iter()
+            .all(|v| status.get(v).is_some_and(|v| v.attempted_at.is_some()))
+        {
+            return Ok(db);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Deletes the rates older than `keep_days` from the store once a day.
+async fn prune_loop(db: Arc<Database>, cfg: Arc<Config>) {
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+    loop {
+        match db.prune(DAY * cfg.db.keep_days).await {
+            Ok(n) => log::debug!("pruned rates: {n}"),
+            Err(err) => log::error!("prune rates, err: {err}"),
+        }
+        tokio::time::sleep(DAY).await;
+    }
+}
+
+/// Looks for arbitrage across all sources once per update interval, the
+/// groups only check their own rates.
+async fn arbitrage_loop(db: Arc<Database>, cfg: Arc<Config>) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(cfg.bot.update_interval)).await;
+        let graph = graph::build_all(&db.get_rates().await, RateType::NoCash, &cfg.src);
+        if let Some(arbitrage) = graph::find_arbitrage(&graph) {
+            log::info!("arbitrage: all, {arbitrage}");
+        }
+    }
+}
+
+async fn run_group(
+    db: Arc<Database>,
+    cfg: Arc<Config>,
+    parent: Source,
+    srcs: Vec<Source>,
+    mut collected: Option<oneshot::Sender<()>>,
+) -> anyhow::Result<()> {
+    let options = srcs.iter().map(|v| cfg.src.options_for(*v));
+    let update_interval = options
+        .clone()
+        .filter_map(|v| v.update_interval)
+        .min()
+        .unwrap_or(cfg.bot.update_interval);
+    let timeout = options
+        .filter_map(|v| v.timeout)
+        .max()
+        .unwrap_or(cfg.bot.reqwest_timeout);
+    let client = reqwest::ClientBuilder::new()
+        .timeout(Duration::from_secs(timeout))
+        .build()?;
+    loop {
+        log::debug!("get rates: {parent}");
+        let status = db.get_status().await;
+        let (active, retries) = active(&srcs, &status, &cfg.bot);
+        if !active.is_empty() {
+            let cb = db.get_rates().await.remove(&Source::Cb).unwrap_or_default();
+            for report in collect_reports(&client, &cfg, parent, &active, retries, &cb).await {
+                apply(&db, report).await;
+            }
+            db.notify_round();
+        }
+        if let Some(tx) = collected.take() {
+            let _ = tx.send(());
+        }
+        tokio::time::sleep(Duration::from_secs(update_interval)).await;
+    }
+}
+
+pub async fn apply(db: &Database, report: Report) {
+    let src = report.src;
+    match report.result {
+        Ok(rates) => db.set_rates(src, rates).await,
+        Err(err) => db.set_failed(src, err.to_string()).await,
+    }
+    db.set_latency(src, report.latency).await;
+    db.set_rejected(src, report.rejected).await;
+}
+
+async fn collect_reports(
+    client: &reqwest::Client,
+    cfg: &Config,
+    parent: Source,
+    srcs: &[Source],
+    retries: u32,
+    cb: &[Rate],
+) -> Vec<Report> {
+    let (result, latency) = collect_with_retries(client, cfg, parent, srcs, retries).await;
+    let results = match result {
+        Ok(results) => results,
+        Err(err) => srcs.iter().map(|v| (*v, Err(anyhow!("{err}")))).collect(),
+    };
+    let mut reports = vec![];
+    for (src, result) in results {
+        let mut rejected = 0;
+        let result = result.and_then(|rates| {
+            let rates = rates
+                .iter()
+                .filter(|v| {
+                    (!v.from.is_empty() && !v.to.is_empty())
+                        && (v.buy.is_some_and(|v| v > Decimal::ZERO)
+                            || v.sell.is_some_and(|v| v > Decimal::ZERO))
+                })
+                .filter(|v| match check(src, v, cb, cfg.bot.max_deviation) {
+                    Some(reason) => {
+                        log::warn!("src: {src}, rejected: {reason}");
+                        rejected += 1;
+                        false
                     }
-                    tx.send((src, rates)).await.unwrap();
-                }
-                Err(err) => log::error!("src: {src}, err: {err}"),
+                    None => true,
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if rates.is_empty() {
+                bail!(Error::NoRates);
             }
+            let graph = graph::build(
+                src,
+                &rates,
+                RateType::NoCash,
+                &cfg.src.options_for(src).fees,
+            );
+            if let Some(arbitrage) = graph::find_arbitrage(&graph) {
+                log::info!("arbitrage: {src}, {arbitrage}");
+            }
+            Ok(rates)
+        });
+        if let Err(err) = &result {
+            log::error!("src: {src}, err: {err}");
+        }
+        reports.push(Report {
+            src,
+            latency,
+            result,
+            rejected,
         });
     }
+    reports
+}
+
+/// Reason to reject the rate: buy above sell, or a value deviating from the
+/// central bank cross rate `cb[from] / cb[to]` by more than `max_deviation`
+/// percent.
+fn check(src: Source, rate: &Rate, cb: &[Rate], max_deviation: Decimal) -> Option<String> {
+    let pair = format!("{}/{} {:?}", rate.from, rate.to, rate.rate_type);
+    if let (Some(buy), Some(sell)) = (rate.buy, rate.sell) {
+        if buy > sell {
+            return Some(format!("{pair}, buy {buy} > sell {sell}"));
+        }
+    }
+    if src == Source::Cb {
+        return None;
+    }
+    let cb_rate = |cur: &Currency| {
+        if cur == &Currency::default() {
+            return Some(Decimal::ONE);
+        }
+        cb.iter()
+            .find(|v| v.rate_type == RateType::Cb && &v.from == cur && v.to == Currency::default())?
+            .buy
+            .filter(|v| *v > Decimal::ZERO)
+    };
+    let reference = cb_rate(&rate.from)? / cb_rate(&rate.to)?;
+    for value in [rate.buy, rate.sell].into_iter().flatten() {
+        let deviation = ((value - reference) / reference).abs() * Decimal::ONE_HUNDRED;
+        if deviation > max_deviation {
+            return Some(format!(
+                "{pair}, {value} deviates {}% from cb {}",
+                deviation.round_dp(2),
+                reference.round_dp(4),
+            ));
+        }
+    }
+    None
+}
+
+type Results = Vec<(Source, anyhow::Result<Vec<Rate>>)>;
+
+/// Collects `parent` and the sources derived from it with a single upstream
+/// request.
+async fn collect_group(
+    client: &reqwest::Client,
+    cfg: &Config,
+    parent: Source,
+    srcs: &[Source],
+) -> anyhow::Result<Results> {
+    if srcs == [parent] {
+        let rates = source::collect(client, &cfg.src, parent).await?;
+        return Ok(vec![(parent, Ok(rates))]);
+    }
+    let shared = source::fetch_shared(client, &cfg.src, parent).await?;
+    Ok(srcs
+        .iter()
+        .map(|v| (*v, source::derive(&shared, &cfg.src, *v)))
+        .collect())
+}
+
+async fn collect_with_retries(
+    client: &reqwest::Client,
+    cfg: &Config,
+    parent: Source,
+    srcs: &[Source],
+    retries: u32,
+) -> (anyhow::Result<Results>, Duration) {
+    let mut delay = Duration::from_secs(cfg.bot.retry_delay);
+    let mut attempt = 0;
+    loop {
+        let started_at = Instant::now();
+        let result = collect_group(client, cfg, parent, srcs).await;
+        let latency = started_at.elapsed();
+        match result {
+            Err(err) if attempt < retries => {
+                log::debug!("src: {parent}, attempt: {attempt}, err: {err}");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return (result, latency),
+        }
+    }
+}
+
+/// Whether the circuit breaker is open for a source, i.e. it failed too many
+/// times in a row and the probe interval did not pass yet.
+pub fn is_paused(status: &Status, cfg: &config::Bot) -> bool {
+    status.failures >= cfg.breaker_threshold
+        && status
+            .attempted_at
+            .and_then(|v| v.elapsed().ok())
+            .is_some_and(|v| v.as_secs() < cfg.breaker_interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::{Fault, Server},
+        store::Store,
+    };
+    use axum::http::StatusCode;
+    use rust_decimal_macros::dec;
+    use std::{sync::LazyLock, time::SystemTime};
+
+    static CFG: LazyLock<Config> =
+        LazyLock::new(|| toml::from_str(include_str!("../config/config.toml")).unwrap());
+
+    #[test]
+    fn test_is_paused() {
+        let mut status = Status {
+            failures: CFG.bot.breaker_threshold - 1,
+            attempted_at: Some(SystemTime::now()),
+            ..Default::default()
+        };
+        assert!(!is_paused(&status, &CFG.bot));
+        status.failures += 1;
+        assert!(is_paused(&status, &CFG.bot));
+        status.attempted_at =
+            Some(SystemTime::now() - Duration::from_secs(CFG.bot.breaker_interval + 1));
+        assert!(!is_paused(&status, &CFG.bot));
+    }
+
+    #[test]
+    fn test_check() {
+        let rate = |from: Currency, to: Currency, rate_type, buy, sell| Rate {
+            from,
+            to,
+            rate_type,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let cb = [
+            rate(
+                Currency::usd(),
+                Currency::default(),
+                RateType::Cb,
+                dec!(390),
+                dec!(390),
+            ),
+            rate(
+                Currency::rub(),
+                Currency::default(),
+                RateType::Cb,
+                dec!(4.5),
+                dec!(4.5),
+            ),
+        ];
+        let check = |v: &Rate| check(Source::Acba, v, &cb, CFG.bot.max_deviation);
+        let usd = |buy, sell| {
+            rate(
+                Currency::usd(),
+                Currency::default(),
+                RateType::NoCash,
+                buy,
+                sell,
+            )
+        };
+        assert!(check(&usd(dec!(386), dec!(392))).is_none());
+        assert!(check(&usd(dec!(392), dec!(386))).is_some());
+        assert!(check(&usd(dec!(3.86), dec!(3.92))).is_some());
+        assert!(check(&usd(dec!(38600), dec!(39200))).is_some());
+        let usd_rub = rate(
+            Currency::usd(),
+            Currency::rub(),
+            RateType::NoCash,
+            dec!(85),
+            dec!(88),
+        );
+        assert!(check(&usd_rub).is_none());
+        let gel = rate(
+            Currency::new("GEL"),
+            Currency::default(),
+            RateType::NoCash,
+            dec!(1),
+            dec!(2),
+        );
+        assert!(check(&gel).is_none());
+        assert!(super::check(Source::Cb, &cb[0], &[], CFG.bot.max_deviation).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_collect_mock() -> anyhow::Result<()> {
+        let server = Server::start().await?;
+        let cfg = server.config();
+        let expected = groups(&cfg).into_values().flatten().collect::<Vec<_>>();
+        let status = run_once(cfg).await?.get_status().await;
+        assert_eq!(status.len(), expected.len());
+        for src in expected {
+            assert!(status[&src].last_error.is_none(), "{src}");
+            assert!(status[&src].rate_count > 0, "{src}");
+        }
+        assert_eq!(server.hits("force.unibank.am"), 1);
+        assert_eq!(server.hits("www.idbanking.am"), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_mock_faults() -> anyhow::Result<()> {
+        let server = Server::start().await?;
+        let mut cfg = server.config();
+        cfg.bot.retries = 1;
+        cfg.bot.retry_delay = 0;
+        cfg.src.sas.options.timeout = Some(1);
+        server.set_fault(
+            "www.acbadigital.am",
+            Fault::Status(StatusCode::INTERNAL_SERVER_ERROR),
+        );
+        server.set_fault("api.cba.am", Fault::Malformed);
+        server.set_fault("www.sas.am", Fault::Hang);
+        server.set_fault("force.unibank.am", Fault::Status(StatusCode::NOT_FOUND));
+        server.set_fault("www.armswissbank.am", Fault::Flaky(1));
+        let status = run_once(cfg).await?.get_status().await;
+        for src in [
+            Source::Acba,
+            Source::Cb,
+            Source::SAS,
+            Source::Unibank,
+            Source::Kwikpay,
+            Source::Unistream,
+        ] {
+            assert!(status[&src].last_error.is_some(), "{src}");
+        }
+        assert_eq!(server.hits("www.acbadigital.am"), 2);
+        assert!(status[&Source::ArmSwiss].updated_at.is_some());
+        assert_eq!(server.hits("www.armswissbank.am"), 2);
+        assert!(status[&Source::AEB].updated_at.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_mock_cb_hang() -> anyhow::Result<()> {
+        let server = Server::start().await?;
+        let mut cfg = server.config();
+        cfg.bot.retries = 2;
+        cfg.bot.retry_delay = 1;
+        cfg.src.cb.options.timeout = Some(1);
+        server.set_fault("api.cba.am", Fault::Hang);
+        let db = Database::new(Store::open_in_memory()?);
+        let started_at = Instant::now();
+        let tasks = spawn_groups(db.clone(), Arc::new(cfg)).await;
+        // the central bank round takes 6s with the retries
+        assert!(started_at.elapsed() < Duration::from_secs(3));
+        loop {
+            let status = db.get_status().await;
+            assert!(!status.contains_key(&Source::Cb));
+            if status
+                .get(&Source::AEB)
+                .is_some_and(|v| v.updated_at.is_some())
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        drop(tasks);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_mock_cb_first() -> anyhow::Result<()> {
+        let server = Server::start().await?;
+        let mut cfg = server.config();
+        // any deviation from the central bank rejects the rate
+        cfg.bot.max_deviation = Decimal::ZERO;
+        let status = run_once(cfg).await?.get_status().await;
+        assert!(status[&Source::Cb].last_error.is_none());
+        assert_eq!(status[&Source::Cb].rejected, 0);
+        assert!(status[&Source::AEB].rejected > 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_mock() -> anyhow::Result<()> {
+        let server = Server::start().await?;
+        let mut cfg = server.config();
+        cfg.bot.retries = 0;
+        server.set_fault(
+            "www.acbadigital.am",
+            Fault::Status(StatusCode::SERVICE_UNAVAILABLE),
+        );
+        let expected = groups(&cfg).into_values().flatten().collect::<Vec<_>>();
+        let db = Database::new(Store::open_in_memory()?);
+        let task = tokio::spawn(run(db.clone(), Arc::new(cfg)));
+        // every source is attempted once before the first update interval
+        let status = loop {
+            assert!(!task.is_finished());
+            let status = db.get_status().await;
+            if expected
+                .iter()
+                .all(|v| status.get(v).is_some_and(|v| v.attempted_at.is_some()))
+            {
+                break status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        task.abort();
+        let aeb = &status[&Source::AEB];
+        assert!(aeb.updated_at.is_some());
+        assert_eq!(aeb.rate_count, 6);
+        let acba = &status[&Source::Acba];
+        assert_eq!(acba.failures, 1);
+        assert!(acba.last_error.is_some());
+        assert_eq!(db.get_rates().await[&Source::Unistream].len(), 2);
+        Ok(())
+    }
 }
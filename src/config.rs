@@ -1,6 +1,11 @@
-use crate::source;
-use serde::Deserialize;
-use std::{env, fs, sync::Arc};
+use crate::{
+    i18n::Lang,
+    source::{self, Currency, RateType},
+};
+use chrono::{FixedOffset, NaiveTime};
+use rust_decimal::Decimal;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{collections::HashMap, env, fs, sync::Arc};
 
 const ENV_CONFIG: &str = "BOT_CONFIG";
 
@@ -8,6 +13,10 @@ const ENV_CONFIG: &str = "BOT_CONFIG";
 pub struct Config {
     pub bot: Bot,
     pub gen: Gen,
+    #[serde(default)]
+    pub db: Db,
+    pub digest: Option<Digest>,
+    pub chart: Option<Chart>,
     pub src: source::Config,
 }
 
@@ -15,10 +24,33 @@ pub struct Config {
 pub struct Bot {
     pub reqwest_timeout: u64,
     pub update_interval: u64,
+    /// Extra attempts per round, none by default.
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default = "retry_delay")]
+    pub retry_delay: u64,
+    /// Failed rounds before a source is paused, never by default.
+    #[serde(default = "breaker_threshold")]
+    pub breaker_threshold: u32,
+    #[serde(default = "breaker_interval")]
+    pub breaker_interval: u64,
+    /// Largest deviation from the central bank cross rate in percent, any by
+    /// default.
+    #[serde(default = "max_deviation")]
+    pub max_deviation: Decimal,
     pub polling: bool,
     pub webhook: Webhook,
+    /// Telegram user ids allowed to run admin commands.
+    #[serde(default)]
+    pub admins: Vec<u64>,
+    /// Alerts a chat may have.
+    #[serde(default = "max_alerts")]
+    pub max_alerts: usize,
 
     pub welcome_msg: String,
+    /// Translations of `welcome_msg`.
+    #[serde(default)]
+    pub welcome_msgs: HashMap<Lang, String>,
     pub name: String,
     pub about: String,
     pub description: String,
@@ -35,6 +67,98 @@ pub struct Webhook {
 pub struct Gen {
     pub rate_dp: u32,
     pub diff_dp: u32,
+    /// Seconds after which the rates of a source expire, never by default.
+    #[serde(default = "max_age")]
+    pub max_age: u64,
+    #[serde(default = "route_hops")]
+    pub route_hops: usize,
+    #[serde(default = "route_srcs")]
+    pub route_srcs: usize,
+    #[serde(default = "route_count")]
+    pub route_count: usize,
+    /// Source categories shown, `*` banks, `#` transfers and `@` cb.
+    #[serde(default = "show")]
+    pub show: String,
+    /// Day boundaries of `/history`, Asia/Yerevan by default.
+    #[serde(default = "yerevan", deserialize_with = "utc_offset")]
+    pub utc_offset: FixedOffset,
+    /// Language of the replies when the user has none.
+    #[serde(default)]
+    pub lang: Lang,
+    /// How `/conv` tables are sent.
+    #[serde(default)]
+    pub table: TableFormat,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+    strum::EnumString,
+    strum::Display,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum TableFormat {
+    #[default]
+    Text,
+    /// PNG, for tables too wide for phones, needs `[chart]`.
+    #[strum(serialize = "image", serialize = "img")]
+    Image,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Db {
+    /// Sqlite file of the store, in memory by default.
+    pub path: String,
+    /// Days of rate history kept in the store.
+    #[serde(default = "keep_days")]
+    pub keep_days: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Digest {
+    /// Local time of day the digest is sent at.
+    #[serde(deserialize_with = "time")]
+    pub time: NaiveTime,
+    #[serde(deserialize_with = "utc_offset")]
+    pub utc_offset: FixedOffset,
+    /// Channel id or `@username` the digest is posted to as well.
+    pub channel: Option<String>,
+    pub pairs: Vec<Pair>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Chart {
+    /// TrueType font of the chart texts.
+    pub font: String,
+    /// Monospaced TrueType font of the table images.
+    pub mono_font: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pair {
+    #[serde(deserialize_with = "source::de::currency")]
+    pub from: Currency,
+    #[serde(deserialize_with = "source::de::currency")]
+    pub to: Currency,
+    #[serde(deserialize_with = "source::de::rate_type")]
+    pub rate_type: RateType,
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Self {
+            path: ":memory:".into(),
+            keep_days: keep_days(),
+        }
+    }
 }
 
 impl Config {
@@ -43,3 +167,85 @@ impl Config {
         Ok(Arc::new(cfg))
     }
 }
+
+fn retry_delay() -> u64 {
+    1
+}
+
+fn breaker_threshold() -> u32 {
+    u32::MAX
+}
+
+fn breaker_interval() -> u64 {
+    1800
+}
+
+fn max_deviation() -> Decimal {
+    Decimal::MAX
+}
+
+fn max_alerts() -> usize {
+    10
+}
+
+fn max_age() -> u64 {
+    u64::MAX
+}
+
+fn route_hops() -> usize {
+    3
+}
+
+fn route_srcs() -> usize {
+    2
+}
+
+fn route_count() -> usize {
+    5
+}
+
+fn show() -> String {
+    "*#@".into()
+}
+
+fn yerevan() -> FixedOffset {
+    FixedOffset::east_opt(4 * 3600).expect("panic")
+}
+
+fn keep_days() -> u32 {
+    90
+}
+
+fn time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M").map_err(de::Error::custom)
+}
+
+fn utc_offset<'de, D>(deserializer: D) -> Result<FixedOffset, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hours = i32::deserialize(deserializer)?;
+    FixedOffset::east_opt(hours * 3600)
+        .ok_or_else(|| de::Error::custom(format!("invalid utc offset: {hours}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_config() -> anyhow::Result<()> {
+        let cfg: Config = toml::from_str(include_str!("../fixtures/config_old.toml"))?;
+        assert_eq!(cfg.db.path, ":memory:");
+        assert_eq!(cfg.bot.retries, 0);
+        assert!(cfg.digest.is_none());
+        assert!(cfg.chart.is_none());
+        #[cfg(feature = "moex")]
+        assert!(cfg.src.moex.options.timeout.is_none());
+        Ok(())
+    }
+}
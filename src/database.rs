@@ -1,27 +1,190 @@
-use crate::source::{Currency, Rate, RateType, Source};
-use std::{collections::HashMap, sync::Arc, time::SystemTime};
-use tokio::sync::Mutex;
+use crate::{
+    alert::{Alert, Op},
+    config,
+    settings::Settings,
+    source::{Currency, Rate, RateType, Source},
+    store::Store,
+};
+use chrono::{DateTime, Days, FixedOffset, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::{Mutex, Notify};
 
 #[derive(Debug)]
 pub struct Database {
     data: Mutex<Data>,
     cache: Mutex<Cache>,
+    /// Sqlite blocks, it is only used from `spawn_blocking`.
+    store: Arc<std::sync::Mutex<Store>>,
+    round: Notify,
 }
 
 #[derive(Debug)]
 pub struct Data {
     rates: HashMap<Source, Vec<Rate>>,
+    status: HashMap<Source, Status>,
     updated_at: SystemTime,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    /// Last successful collection.
+    pub updated_at: Option<SystemTime>,
+    /// Last collection, successful or not.
+    pub attempted_at: Option<SystemTime>,
+    /// Rates were restored from the store and not refreshed yet.
+    pub stale: bool,
+    pub last_error: Option<String>,
+    /// Consecutive failed collections.
+    pub failures: u32,
+    pub latency: Option<Duration>,
+    pub rate_count: usize,
+    /// Rates dropped by the sanity checks in the last collection.
+    pub rejected: usize,
+}
+
+/// Open, high, low and close of a rate over a day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ohlc {
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+}
+
+impl Ohlc {
+    fn new(value: Decimal) -> Self {
+        Self {
+            open: value,
+            high: value,
+            low: value,
+            close: value,
+        }
+    }
+
+    fn push(&mut self, value: Decimal) {
+        self.high = self.high.max(value);
+        self.low = self.low.min(value);
+        self.close = value;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DayRate {
+    pub buy: Option<Ohlc>,
+    pub sell: Option<Ohlc>,
+}
+
+/// Rates of a pair per local day and source.
+pub type History = BTreeMap<NaiveDate, HashMap<Source, DayRate>>;
+
+/// Aggregates the rates ordered by time into days at `utc_offset`.
+pub fn aggregate(rates: &[(Source, SystemTime, Rate)], utc_offset: FixedOffset) -> History {
+    let push = |ohlc: &mut Option<Ohlc>, value: Option<Decimal>| {
+        let Some(value) = value else {
+            return;
+        };
+        match ohlc {
+            Some(v) => v.push(value),
+            None => *ohlc = Some(Ohlc::new(value)),
+        }
+    };
+    let mut history = History::new();
+    for (src, ts, rate) in rates {
+        let date = DateTime::<Utc>::from(*ts)
+            .with_timezone(&utc_offset)
+            .date_naive();
+        let day = history.entry(date).or_default().entry(*src).or_default();
+        push(&mut day.buy, rate.buy);
+        push(&mut day.sell, rate.sell);
+    }
+    history
+}
+
 impl Data {
     fn get_rates(&self) -> HashMap<Source, Vec<Rate>> {
         self.rates.clone()
     }
 
-    fn set_rates(&mut self, src: Source, rates: Vec<Rate>) {
-        self.rates.insert(src, rates);
-        self.updated_at = SystemTime::now();
+    /// Returns if the tables of the source may change, its rates changed or
+    /// it recovered from failures and may have expired meanwhile.
+    fn set_rates(&mut self, src: Source, rates: Vec<Rate>, updated_at: SystemTime) -> bool {
+        let status = self.status.entry(src).or_default();
+        let recovered = status.failures > 0;
+        status.updated_at = Some(updated_at);
+        status.attempted_at = Some(updated_at);
+        status.stale = false;
+        status.failures = 0;
+        status.rate_count = rates.len();
+        self.updated_at = updated_at;
+        self.rates.insert(src, rates.clone()).as_ref() != Some(&rates) || recovered
+    }
+
+    /// Currencies quoted by the source.
+    fn get_currencies(&self, src: Source) -> HashSet<Currency> {
+        self.rates
+            .get(&src)
+            .into_iter()
+            .flatten()
+            .flat_map(|v| [v.from.clone(), v.to.clone()])
+            .collect()
+    }
+
+    fn set_failed(&mut self, src: Source, err: String, attempted_at: SystemTime) {
+        let status = self.status.entry(src).or_default();
+        status.attempted_at = Some(attempted_at);
+        status.last_error = Some(err);
+        status.failures += 1;
+    }
+
+    fn set_latency(&mut self, src: Source, latency: Duration) {
+        self.status.entry(src).or_default().latency = Some(latency);
+    }
+
+    fn set_rejected(&mut self, src: Source, rejected: usize) {
+        self.status.entry(src).or_default().rejected = rejected;
+    }
+
+    fn load_rates(&mut self, snapshot: HashMap<Source, (SystemTime, Vec<Rate>)>) {
+        let mut last_updated_at = None;
+        for (src, (updated_at, rates)) in snapshot {
+            if self.rates.contains_key(&src) {
+                continue;
+            }
+            self.status.insert(
+                src,
+                Status {
+                    updated_at: Some(updated_at),
+                    stale: true,
+                    rate_count: rates.len(),
+                    ..Default::default()
+                },
+            );
+            self.rates.insert(src, rates);
+            last_updated_at = last_updated_at.max(Some(updated_at));
+        }
+        if let Some(updated_at) = last_updated_at {
+            self.updated_at = updated_at;
+        }
+    }
+
+    fn get_status(&self) -> HashMap<Source, Status> {
+        self.status.clone()
+    }
+
+    fn get_stale(&self) -> Vec<Source> {
+        let mut stale = self
+            .status
+            .iter()
+            .filter(|(_, v)| v.stale)
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+        stale.sort();
+        stale
     }
 
     fn get_updated_at(&self) -> SystemTime {
@@ -29,18 +192,22 @@ impl Data {
     }
 }
 
+/// Rendered tables by pair and by source, keyed by the other options.
 #[derive(Debug)]
 pub struct Cache {
-    conv: HashMap<String, String>,
-    src: HashMap<String, String>,
+    conv: HashMap<(Currency, Currency), HashMap<String, String>>,
+    src: HashMap<Source, HashMap<String, String>>,
 }
 
 impl Cache {
     const KEY_SEP: &'static str = "_";
 
-    fn clear(&mut self) {
-        self.conv.clear();
-        self.src.clear();
+    /// Drops the tables of the source and of the pairs it may quote, a path
+    /// of the source only goes through `currencies`.
+    fn invalidate(&mut self, src: Source, currencies: &HashSet<Currency>) {
+        self.src.remove(&src);
+        self.conv
+            .retain(|(from, to), _| !(currencies.contains(from) && currencies.contains(to)));
     }
 
     fn add_conv(
@@ -49,20 +216,32 @@ impl Cache {
         to: &Currency,
         rate_type: RateType,
         inv: bool,
+        gen: &config::Gen,
         value: String,
     ) {
+        let key = self.format_conv_key(rate_type, inv, gen);
         self.conv
-            .insert(self.format_conv_key(from, to, rate_type, inv), value);
+            .entry((from.clone(), to.clone()))
+            .or_default()
+            .insert(key, value);
+    }
+
+    fn add_src(&mut self, src: Source, rate_type: RateType, gen: &config::Gen, value: String) {
+        let key = self.format_src_key(rate_type, gen);
+        self.src.entry(src).or_default().insert(key, value);
     }
 
-    fn add_src(&mut self, src: Source, rate_type: RateType, value: String) {
-        self.src.insert(self.format_src_key(src, rate_type), value);
+    fn format_src_key(&self, rate_type: RateType, gen: &config::Gen) -> String {
+        [(rate_type as u8).to_string(), self.format_gen_key(gen)].join(Self::KEY_SEP)
     }
 
-    fn format_src_key(&self, src: Source, rate_type: RateType) -> String {
+    /// Output options of `gen` that users can override.
+    fn format_gen_key(&self, gen: &config::Gen) -> String {
         [
-            src.to_string().to_lowercase(),
-            (rate_type as u8).to_string(),
+            gen.rate_dp.to_string(),
+            gen.diff_dp.to_string(),
+            gen.show.clone(),
+            gen.lang.to_string(),
         ]
         .join(Self::KEY_SEP)
     }
@@ -73,28 +252,26 @@ impl Cache {
         to: &Currency,
         rate_type: RateType,
         inv: bool,
+        gen: &config::Gen,
     ) -> Option<String> {
         self.conv
-            .get(&self.format_conv_key(from, to, rate_type, inv))
+            .get(&(from.clone(), to.clone()))?
+            .get(&self.format_conv_key(rate_type, inv, gen))
             .cloned()
     }
 
-    fn get_src(&self, src: Source, rate_type: RateType) -> Option<String> {
-        self.src.get(&self.format_src_key(src, rate_type)).cloned()
+    fn get_src(&self, src: Source, rate_type: RateType, gen: &config::Gen) -> Option<String> {
+        self.src
+            .get(&src)?
+            .get(&self.format_src_key(rate_type, gen))
+            .cloned()
     }
 
-    fn format_conv_key(
-        &self,
-        from: &Currency,
-        to: &Currency,
-        rate_type: RateType,
-        inv: bool,
-    ) -> String {
+    fn format_conv_key(&self, rate_type: RateType, inv: bool, gen: &config::Gen) -> String {
         [
-            from.to_string().to_lowercase(),
-            to.to_string().to_uppercase(),
             (rate_type as u8).to_string(),
             (inv as i32).to_string(),
+            self.format_gen_key(gen),
         ]
         .join(Self::KEY_SEP)
     }
@@ -102,37 +279,217 @@ impl Cache {
 
 impl Database {
     #[must_use]
-    pub fn new() -> Arc<Self> {
+    pub fn new(store: Store) -> Arc<Self> {
         Arc::new(Self {
             data: Mutex::new(Data {
                 rates: HashMap::new(),
+                status: HashMap::new(),
                 updated_at: SystemTime::now(),
             }),
             cache: Mutex::new(Cache {
                 conv: HashMap::new(),
                 src: HashMap::new(),
             }),
+            store: Arc::new(std::sync::Mutex::new(store)),
+            round: Notify::new(),
         })
     }
 
+    /// Runs `f` with the store on the blocking thread pool.
+    async fn with_store<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&mut Store) -> anyhow::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || f(&mut store.lock().unwrap())).await?
+    }
+
     pub async fn get_rates(&self) -> HashMap<Source, Vec<Rate>> {
         let data = self.data.lock().await;
         data.get_rates()
     }
 
     pub async fn set_rates(&self, src: Source, rates: Vec<Rate>) {
+        let now = SystemTime::now();
+        let appended = rates.clone();
+        if let Err(err) = self
+            .with_store(move |store| store.append(src, now, &appended))
+            .await
+        {
+            log::error!("store append: {src}, err: {err}");
+        }
         let mut data = self.data.lock().await;
-        data.set_rates(src, rates);
+        let mut currencies = data.get_currencies(src);
+        if data.set_rates(src, rates, now) {
+            currencies.extend(data.get_currencies(src));
+            self.cache.lock().await.invalidate(src, &currencies);
+        }
     }
 
-    pub async fn clear_cache(&self) {
-        let mut cache = self.cache.lock().await;
-        cache.clear();
+    /// The rates of a failing source expire, its tables are rendered again.
+    pub async fn set_failed(&self, src: Source, err: String) {
+        let mut data = self.data.lock().await;
+        data.set_failed(src, err, SystemTime::now());
+        let currencies = data.get_currencies(src);
+        self.cache.lock().await.invalidate(src, &currencies);
+    }
+
+    pub async fn set_latency(&self, src: Source, latency: Duration) {
+        let mut data = self.data.lock().await;
+        data.set_latency(src, latency);
+    }
+
+    pub async fn set_rejected(&self, src: Source, rejected: usize) {
+        let mut data = self.data.lock().await;
+        data.set_rejected(src, rejected);
+    }
+
+    pub async fn get_status(&self) -> HashMap<Source, Status> {
+        let data = self.data.lock().await;
+        data.get_status()
+    }
+
+    /// Deletes the rates older than `keep` from the store.
+    pub async fn prune(&self, keep: Duration) -> anyhow::Result<usize> {
+        let before = SystemTime::now() - keep;
+        self.with_store(move |store| store.prune(before)).await
+    }
+
+    pub async fn load_snapshot(&self) -> anyhow::Result<()> {
+        let snapshot = self.with_store(|store| store.get_latest()).await?;
+        let mut data = self.data.lock().await;
+        data.load_rates(snapshot);
+        Ok(())
     }
 
-    pub async fn get_cache_src(&self, src: Source, rate_type: RateType) -> Option<String> {
+    /// Rates of the pair over the last `days` including today, from every
+    /// source or only from `src`.
+    pub async fn get_pair_rates(
+        &self,
+        src: Option<Source>,
+        from: &Currency,
+        to: &Currency,
+        rate_type: RateType,
+        days: u32,
+        utc_offset: FixedOffset,
+    ) -> anyhow::Result<Vec<(Source, SystemTime, Rate)>> {
+        let now = Utc::now().with_timezone(&utc_offset);
+        let since = now
+            .date_naive()
+            .checked_sub_days(Days::new(days.saturating_sub(1) as u64))
+            .unwrap_or(NaiveDate::MIN)
+            .and_time(Default::default())
+            .and_local_timezone(utc_offset)
+            .single()
+            .map(SystemTime::from)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let (from, to) = (from.clone(), to.clone());
+        self.with_store(move |store| {
+            store.get_pair_rates(src, &from, &to, rate_type, since, now.into())
+        })
+        .await
+    }
+
+    /// Rates of the pair per day, see `get_pair_rates`.
+    pub async fn get_history(
+        &self,
+        src: Option<Source>,
+        from: &Currency,
+        to: &Currency,
+        rate_type: RateType,
+        days: u32,
+        utc_offset: FixedOffset,
+    ) -> anyhow::Result<History> {
+        let rates = self
+            .get_pair_rates(src, from, to, rate_type, days, utc_offset)
+            .await?;
+        Ok(aggregate(&rates, utc_offset))
+    }
+
+    /// Signals that a collection round finished.
+    pub fn notify_round(&self) {
+        self.round.notify_one();
+    }
+
+    /// Waits for the next collection round, rounds finished meanwhile or
+    /// within `quiet` of each other are coalesced into one, as the groups
+    /// finish one after another.
+    pub async fn wait_round(&self, quiet: Duration) {
+        self.round.notified().await;
+        while tokio::time::timeout(quiet, self.round.notified())
+            .await
+            .is_ok()
+        {}
+    }
+
+    pub async fn add_alert(
+        &self,
+        chat_id: i64,
+        from: &Currency,
+        to: &Currency,
+        op: Op,
+        value: Decimal,
+        rate_type: RateType,
+    ) -> anyhow::Result<i64> {
+        let (from, to) = (from.clone(), to.clone());
+        self.with_store(move |store| store.add_alert(chat_id, &from, &to, op, value, rate_type))
+            .await
+    }
+
+    pub async fn get_alerts(&self, chat_id: Option<i64>) -> anyhow::Result<Vec<Alert>> {
+        self.with_store(move |store| store.get_alerts(chat_id))
+            .await
+    }
+
+    pub async fn delete_alert(&self, chat_id: i64, id: i64) -> anyhow::Result<bool> {
+        self.with_store(move |store| store.delete_alert(chat_id, id))
+            .await
+    }
+
+    pub async fn set_alert_triggered(&self, id: i64, triggered: bool) -> anyhow::Result<()> {
+        self.with_store(move |store| store.set_alert_triggered(id, triggered))
+            .await
+    }
+
+    pub async fn add_digest(&self, chat_id: i64) -> anyhow::Result<bool> {
+        self.with_store(move |store| store.add_digest(chat_id))
+            .await
+    }
+
+    pub async fn delete_digest(&self, chat_id: i64) -> anyhow::Result<bool> {
+        self.with_store(move |store| store.delete_digest(chat_id))
+            .await
+    }
+
+    pub async fn get_digests(&self) -> anyhow::Result<Vec<i64>> {
+        self.with_store(|store| store.get_digests()).await
+    }
+
+    pub async fn get_settings(&self, user_id: i64) -> anyhow::Result<Settings> {
+        self.with_store(move |store| store.get_settings(user_id))
+            .await
+    }
+
+    pub async fn set_settings(&self, user_id: i64, settings: &Settings) -> anyhow::Result<()> {
+        let settings = settings.clone();
+        self.with_store(move |store| store.set_settings(user_id, &settings))
+            .await
+    }
+
+    pub async fn get_stale(&self) -> Vec<Source> {
+        let data = self.data.lock().await;
+        data.get_stale()
+    }
+
+    pub async fn get_cache_src(
+        &self,
+        src: Source,
+        rate_type: RateType,
+        gen: &config::Gen,
+    ) -> Option<String> {
         let cache = self.cache.lock().await;
-        cache.get_src(src, rate_type)
+        cache.get_src(src, rate_type, gen)
     }
 
     pub async fn get_cache_conv(
@@ -141,14 +498,21 @@ impl Database {
         to: &Currency,
         rate_type: RateType,
         inv: bool,
+        gen: &config::Gen,
     ) -> Option<String> {
         let cache = self.cache.lock().await;
-        cache.get_conv(from, to, rate_type, inv)
+        cache.get_conv(from, to, rate_type, inv, gen)
     }
 
-    pub async fn set_cache_src(&self, src: Source, rate_type: RateType, value: String) {
+    pub async fn set_cache_src(
+        &self,
+        src: Source,
+        rate_type: RateType,
+        gen: &config::Gen,
+        value: String,
+    ) {
         let mut cache = self.cache.lock().await;
-        cache.add_src(src, rate_type, value);
+        cache.add_src(src, rate_type, gen, value);
     }
 
     pub async fn set_cache_conv(
@@ -157,10 +521,11 @@ impl Database {
         to: &Currency,
         rate_type: RateType,
         inv: bool,
+        gen: &config::Gen,
         value: String,
     ) {
         let mut cache = self.cache.lock().await;
-        cache.add_conv(from, to, rate_type, inv, value);
+        cache.add_conv(from, to, rate_type, inv, gen, value);
     }
 
     pub async fn get_updated_at(&self) -> SystemTime {
@@ -168,3 +533,172 @@ impl Database {
         data.get_updated_at()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_aggregate() {
+        let offset = FixedOffset::east_opt(4 * 3600).expect("panic");
+        let ts = |s: &str| SystemTime::from(DateTime::parse_from_rfc3339(s).expect("panic"));
+        let rate = |buy, sell| Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell,
+        };
+        let rates = [
+            (
+                Source::Acba,
+                ts("2024-03-01T19:00:00Z"),
+                rate(dec!(386), None),
+            ),
+            (
+                Source::Acba,
+                ts("2024-03-01T21:00:00Z"),
+                rate(dec!(387), Some(dec!(391))),
+            ),
+            (
+                Source::Acba,
+                ts("2024-03-02T08:00:00Z"),
+                rate(dec!(385), Some(dec!(390))),
+            ),
+            (
+                Source::Acba,
+                ts("2024-03-02T12:00:00Z"),
+                rate(dec!(386), Some(dec!(392))),
+            ),
+            (
+                Source::Ameria,
+                ts("2024-03-02T12:00:00Z"),
+                rate(dec!(388), None),
+            ),
+        ];
+        let history = aggregate(&rates, offset);
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%F").expect("panic");
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history[&day("2024-03-01")][&Source::Acba].buy,
+            Some(Ohlc::new(dec!(386)))
+        );
+        let acba = history[&day("2024-03-02")][&Source::Acba];
+        assert_eq!(
+            acba.buy,
+            Some(Ohlc {
+                open: dec!(387),
+                high: dec!(387),
+                low: dec!(385),
+                close: dec!(386),
+            })
+        );
+        assert_eq!(
+            acba.sell.map(|v| (v.low, v.high)),
+            Some((dec!(390), dec!(392)))
+        );
+        assert_eq!(history[&day("2024-03-02")][&Source::Ameria].sell, None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_invalidate() -> anyhow::Result<()> {
+        let cfg: config::Config = toml::from_str(include_str!("../config/config.toml"))?;
+        let gen = &cfg.gen;
+        let db = Database::new(Store::open_in_memory()?);
+        let rate = |from: Currency, buy| Rate {
+            from,
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell: None,
+        };
+        let usd = || rate(Currency::usd(), dec!(386));
+        db.set_rates(Source::Acba, vec![usd()]).await;
+        let amd = Currency::default();
+        let fill = || async {
+            for (from, to) in [(&amd, &Currency::usd()), (&Currency::rub(), &amd)] {
+                db.set_cache_conv(from, to, RateType::NoCash, false, gen, "".into())
+                    .await;
+            }
+            for src in [Source::Acba, Source::Ameria] {
+                db.set_cache_src(src, RateType::NoCash, gen, "".into())
+                    .await;
+            }
+        };
+        let cached = || async {
+            (
+                db.get_cache_conv(&amd, &Currency::usd(), RateType::NoCash, false, gen)
+                    .await
+                    .is_some(),
+                db.get_cache_conv(&Currency::rub(), &amd, RateType::NoCash, false, gen)
+                    .await
+                    .is_some(),
+                db.get_cache_src(Source::Acba, RateType::NoCash, gen)
+                    .await
+                    .is_some(),
+                db.get_cache_src(Source::Ameria, RateType::NoCash, gen)
+                    .await
+                    .is_some(),
+            )
+        };
+        fill().await;
+        // the same rates keep the tables
+        db.set_rates(Source::Acba, vec![usd()]).await;
+        assert_eq!(cached().await, (true, true, true, true));
+        db.set_rates(Source::Acba, vec![rate(Currency::usd(), dec!(387))])
+            .await;
+        assert_eq!(cached().await, (false, true, false, true));
+        fill().await;
+        // a new currency of the source drops its pairs
+        db.set_rates(
+            Source::Acba,
+            vec![
+                rate(Currency::usd(), dec!(387)),
+                rate(Currency::rub(), dec!(4.6)),
+            ],
+        )
+        .await;
+        assert_eq!(cached().await, (false, false, false, true));
+        fill().await;
+        db.set_failed(Source::Acba, "err".into()).await;
+        assert_eq!(cached().await, (false, false, false, true));
+        fill().await;
+        // recovered with the same rates, they were expired meanwhile
+        db.set_rates(
+            Source::Acba,
+            vec![
+                rate(Currency::usd(), dec!(387)),
+                rate(Currency::rub(), dec!(4.6)),
+            ],
+        )
+        .await;
+        assert_eq!(cached().await, (false, false, false, true));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_round() -> anyhow::Result<()> {
+        const QUIET: Duration = Duration::from_millis(50);
+        let db = Database::new(Store::open_in_memory()?);
+        let wait = || tokio::time::timeout(Duration::from_millis(200), db.wait_round(QUIET));
+        db.notify_round();
+        db.notify_round();
+        wait().await?;
+        assert!(wait().await.is_err());
+        let notify = {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for _ in 0..3 {
+                    db.notify_round();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+        };
+        wait().await?;
+        notify.await?;
+        // the rounds close to each other were coalesced into one
+        assert!(wait().await.is_err());
+        Ok(())
+    }
+}
@@ -1,43 +1,76 @@
 use crate::{
-    config, graph,
+    alert::Alert,
+    collector,
+    config::{self, Config},
+    database::{History, Status},
+    graph,
+    i18n::Text,
     source::{Currency, Rate, RateType, Source},
+    table::{decimal_to_string, ConvRow, ConvTable, Format, SrcRow, SrcTable, AMOUNT_DP},
 };
-use rust_decimal::{Decimal, RoundingStrategy};
-use std::{collections::HashMap, fmt::Write};
+use rust_decimal::Decimal;
+use std::{collections::HashMap, fmt::Write, time::Duration};
+use strum::IntoEnumIterator;
 
+/// Conversion rates from every source, or with `amount` of `from` the amount
+/// of `to` received and the loss in AMD versus the best bank.
+#[allow(clippy::too_many_arguments)]
 pub fn conv_table(
     from: &Currency,
     to: &Currency,
+    amount: Option<Decimal>,
     rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
     rate_type: RateType,
     inv: bool,
-    cfg: &config::Gen,
+    cfg: &Config,
+    gen: &config::Gen,
 ) -> String {
-    if from.is_empty() || to.is_empty() {
-        return "".into();
-    }
-
-    #[derive(Debug)]
-    struct Row {
-        src: Source,
-        rate: Decimal,
-        rate_str: String,
-        diff: Decimal,
-        diff_str: String,
-        path: Vec<Currency>,
-    }
+    conv_rates(from, to, amount, rates, status, rate_type, inv, cfg, gen).render(
+        Format::Text,
+        gen.rate_dp,
+        gen.diff_dp,
+    )
+}
 
+/// Rows of `conv_table`.
+#[allow(clippy::too_many_arguments)]
+pub fn conv_rates(
+    from: &Currency,
+    to: &Currency,
+    amount: Option<Decimal>,
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    rate_type: RateType,
+    inv: bool,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> ConvTable {
     let mut table = vec![];
-    let mut src_width: usize = 0;
-    let mut rate_width: usize = 0;
+    if from.is_empty() || to.is_empty() {
+        return ConvTable {
+            from: from.clone(),
+            to: to.clone(),
+            amount,
+            rate_type,
+            inv,
+            rows: table,
+        };
+    }
     let sort = if inv {
         |a: Decimal, b: Decimal| a.partial_cmp(&b).expect("panic")
     } else {
         |a: Decimal, b: Decimal| b.partial_cmp(&a).expect("panic")
     };
     'outer: for (src, rates) in rates {
-        let graph = graph::build(&rates, rate_type);
-        let mut paths = graph::find_all_paths(&graph, from, to);
+        if !is_shown(*src, status, gen) {
+            continue;
+        }
+        let graph = graph::build(*src, rates, rate_type, &cfg.src.options_for(*src).fees);
+        let mut paths = graph::find_all_paths(&graph, from, to, amount, graph::Limits::default())
+            .into_iter()
+            .map(|v| (v.currencies, v.rate, amount.unwrap_or_default() * v.rate))
+            .collect::<Vec<_>>();
         if paths.is_empty() {
             continue;
         }
@@ -60,17 +93,15 @@ pub fn conv_table(
                 }
             }
         }
-        src_width = src_width.max(src.to_string().len());
-        for (path, rate) in paths {
-            let rate_str = decimal_to_string(rate, cfg.rate_dp);
-            rate_width = rate_width.max(rate_str.len());
-            table.push(Row {
+        for (path, rate, received) in paths {
+            table.push(ConvRow {
                 src: *src,
+                rate_type,
                 rate,
-                rate_str,
-                diff: Decimal::ZERO,
-                diff_str: "".into(),
+                diff: None,
+                received,
                 path: path.clone(),
+                updated_at: status.get(src).and_then(|v| v.updated_at),
             });
         }
     }
@@ -78,12 +109,14 @@ pub fn conv_table(
         std::cmp::Ordering::Equal => a.src.cmp(&b.src),
         other => other,
     });
-    let best_rate = table
-        .iter()
-        .filter(|r| r.src.is_bank())
-        .map(|r| r.rate)
-        .next()
-        .unwrap_or_default();
+    let best = table.iter().find(|r| r.src.is_bank());
+    let best_rate = best.map(|r| r.rate).unwrap_or_default();
+    let best_received = best.map(|r| r.received).unwrap_or_default();
+    let to_amd = if to == &Currency::default() {
+        Some(Decimal::ONE)
+    } else {
+        cb_rate(to, rates)
+    };
     let mut is_desc = false;
     let mut rate = Decimal::ZERO;
     for (idx, row) in table.iter().enumerate() {
@@ -98,96 +131,467 @@ pub fn conv_table(
             break;
         }
     }
-    let mut diff_width: usize = 0;
     for row in table.iter_mut() {
-        if row.rate.is_zero() {
+        if amount.is_some() {
+            if let Some(to_amd) = to_amd {
+                row.diff = Some((best_received - row.received) * to_amd);
+            }
             continue;
         }
-        row.diff = ((best_rate - row.rate) / row.rate) * Decimal::ONE_HUNDRED;
-        if is_desc && !row.diff.is_zero() {
-            row.diff = -row.diff;
+        if row.rate.is_zero() {
+            continue;
         }
-        row.diff_str = decimal_to_string(row.diff, cfg.diff_dp);
-        diff_width = diff_width.max(row.diff_str.len());
+        let diff = ((best_rate - row.rate) / row.rate) * Decimal::ONE_HUNDRED;
+        row.diff = Some(if is_desc && !diff.is_zero() {
+            -diff
+        } else {
+            diff
+        });
+    }
+    ConvTable {
+        from: from.clone(),
+        to: to.clone(),
+        amount,
+        rate_type,
+        inv,
+        rows: table,
+    }
+}
+
+/// Best rate of `from` in `to` among fresh banks, the top row of
+/// `conv_table` without the stale rates restored from the store.
+pub fn best_rate(
+    from: &Currency,
+    to: &Currency,
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    rate_type: RateType,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Option<(Source, Decimal)> {
+    rates
+        .iter()
+        .filter(|(src, _)| {
+            src.is_bank()
+                && is_shown(**src, status, gen)
+                && !status.get(src).is_some_and(|v| v.stale)
+        })
+        .filter_map(|(src, rates)| {
+            let graph = graph::build(*src, rates, rate_type, &cfg.src.options_for(*src).fees);
+            graph::find_all_paths(&graph, from, to, None, graph::Limits::default())
+                .into_iter()
+                .map(|v| v.rate)
+                .max()
+                .map(|v| (*src, v))
+        })
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+
+/// Both directions of the pair as `/conv` shows them with the `from` and
+/// `to` of each, the first one with the price of `to` when it is AMD. Empty
+/// tables are skipped.
+pub fn conv_tables(
+    from: &Currency,
+    to: &Currency,
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    rate_type: RateType,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Vec<(Currency, Currency, String)> {
+    let inv = to == &Currency::default();
+    [(from, to, !inv), (to, from, inv)]
+        .into_iter()
+        .map(|(from, to, is_inv)| {
+            let s = conv_table(from, to, None, rates, status, rate_type, is_inv, cfg, gen);
+            (from.clone(), to.clone(), s)
+        })
+        .filter(|v| !v.2.is_empty())
+        .collect()
+}
+
+/// Digest sections, the title and the tables of every pair.
+pub fn digest(
+    pairs: &[config::Pair],
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> Vec<(String, Vec<String>)> {
+    pairs
+        .iter()
+        .filter_map(|pair| {
+            let tables = conv_tables(
+                &pair.from,
+                &pair.to,
+                rates,
+                status,
+                pair.rate_type,
+                cfg,
+                gen,
+            )
+            .into_iter()
+            .map(|v| v.2)
+            .collect::<Vec<_>>();
+            if tables.is_empty() {
+                return None;
+            }
+            let title = format!(
+                "{}/{} {}",
+                pair.from,
+                pair.to,
+                Text::from(pair.rate_type).tr(gen.lang)
+            );
+            Some((title, tables))
+        })
+        .collect()
+}
+
+/// Notification for the alert crossed by the best `rate` of `src`.
+pub fn alert_note(alert: &Alert, src: Source, rate: Decimal, cfg: &config::Gen) -> String {
+    let alert = format!(
+        "#{} {}/{} {} {} {}",
+        alert.id,
+        alert.from,
+        alert.to,
+        alert.op,
+        alert.value,
+        Text::from(alert.rate_type).tr(cfg.lang)
+    );
+    format!(
+        "{}: {} {}",
+        Text::Alert.fmt(cfg.lang, alert),
+        decimal_to_string(rate, cfg.rate_dp),
+        src.to_string().to_lowercase(),
+    )
+}
+
+/// Best routes from `from` to `to` over the combined graph of all sources,
+/// possibly changing source between steps.
+#[allow(clippy::too_many_arguments)]
+pub fn route_table(
+    from: &Currency,
+    to: &Currency,
+    amount: Option<Decimal>,
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    rate_type: RateType,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> String {
+    if from.is_empty() || to.is_empty() || from == to {
+        return "".into();
     }
+    let rates = rates
+        .iter()
+        .filter(|(src, _)| is_shown(**src, status, gen))
+        .map(|(src, rates)| (*src, rates.clone()))
+        .collect();
+    let graph = graph::build_all(&rates, rate_type, &cfg.src);
+    let limits = graph::Limits {
+        hops: gen.route_hops,
+        srcs: gen.route_srcs,
+    };
+    let mut paths = graph::find_all_paths(&graph, from, to, amount, limits);
+    paths.sort_by(|a, b| match b.rate.cmp(&a.rate) {
+        std::cmp::Ordering::Equal => a.srcs.len().cmp(&b.srcs.len()),
+        other => other,
+    });
+    paths.truncate(gen.route_count);
+    let inv = to != &Currency::default();
+    let table = paths
+        .iter()
+        .map(|v| {
+            let value = match amount {
+                Some(amount) => decimal_to_string(amount * v.rate, AMOUNT_DP),
+                None if inv => decimal_to_string(Decimal::ONE / v.rate, gen.rate_dp),
+                None => decimal_to_string(v.rate, gen.rate_dp),
+            };
+            let mut route = v.currencies[0].to_string();
+            for (src, cur) in v.srcs.iter().zip(&v.currencies[1..]) {
+                write!(&mut route, " >{src}> {cur}").unwrap();
+            }
+            (value, route)
+        })
+        .collect::<Vec<_>>();
+    let value_width = table.iter().map(|v| v.0.len()).max().unwrap_or(0);
     let mut s = String::new();
-    for row in table {
-        writeln!(
-            &mut s,
-            "{} {:<src_width$} | {:<rate_width$} | {:>diff_width$} | {}",
-            row.src.prefix(),
-            row.src,
-            row.rate_str,
-            row.diff_str,
-            row.path
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>()
-                .join("/"),
-        )
-        .unwrap();
+    for (value, route) in table {
+        writeln!(&mut s, "{value:<value_width$} | {route}").unwrap();
     }
     s
 }
 
-fn decimal_to_string(value: Decimal, dp: u32) -> String {
-    value
-        .round_dp_with_strategy(dp, RoundingStrategy::MidpointAwayFromZero)
-        .normalize()
-        .to_string()
+/// Arbitrage cycles of every fresh source and of all sources combined.
+pub fn arbitrage_table(
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    rate_type: RateType,
+    cfg: &Config,
+    gen: &config::Gen,
+) -> String {
+    let rates = rates
+        .iter()
+        .filter(|(src, _)| !is_expired(**src, status, gen))
+        .map(|(src, rates)| (*src, rates.clone()))
+        .collect::<HashMap<_, _>>();
+    let mut srcs = rates.keys().copied().collect::<Vec<_>>();
+    srcs.sort_by_key(|v| v.to_string());
+    let mut s = String::new();
+    for src in srcs {
+        let graph = graph::build(src, &rates[&src], rate_type, &cfg.src.options_for(src).fees);
+        if let Some(arbitrage) = graph::find_arbitrage(&graph) {
+            writeln!(&mut s, "{src}: {arbitrage}").unwrap();
+        }
+    }
+    let graph = graph::build_all(&rates, rate_type, &cfg.src);
+    if let Some(arbitrage) = graph::find_arbitrage(&graph) {
+        writeln!(&mut s, "all: {arbitrage}").unwrap();
+    }
+    s
+}
+
+/// AMD per unit of `cur` by the central bank.
+fn cb_rate(cur: &Currency, rates: &HashMap<Source, Vec<Rate>>) -> Option<Decimal> {
+    rates
+        .get(&Source::Cb)?
+        .iter()
+        .find(|v| v.rate_type == RateType::Cb && &v.from == cur && v.to == Currency::default())?
+        .buy
+}
+
+fn get_age(src: Source, status: &HashMap<Source, Status>) -> Option<Duration> {
+    status.get(&src)?.updated_at?.elapsed().ok()
+}
+
+/// Rates older than `max_age`, the ones restored from the store are kept
+/// until refreshed and shown as stale.
+fn is_expired(src: Source, status: &HashMap<Source, Status>, cfg: &config::Gen) -> bool {
+    !status.get(&src).is_some_and(|v| v.stale)
+        && get_age(src, status).is_some_and(|v| v.as_secs() > cfg.max_age)
+}
+
+/// Sources of the tables, not expired and in the shown categories.
+fn is_shown(src: Source, status: &HashMap<Source, Status>, cfg: &config::Gen) -> bool {
+    !is_expired(src, status, cfg) && cfg.show.contains(src.prefix())
+}
+
+fn duration_to_string(value: Duration) -> String {
+    let secs = value.as_secs();
+    let (days, hours, mins) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {mins}m")
+    } else {
+        format!("{mins}m")
+    }
+}
+
+pub fn src_age(src: Source, status: &HashMap<Source, Status>, cfg: &config::Gen) -> String {
+    let Some(age) = get_age(src, status) else {
+        return "".into();
+    };
+    let mut s = Text::Updated.fmt(cfg.lang, duration_to_string(age));
+    if age.as_secs() > cfg.max_age || status.get(&src).is_some_and(|v| v.stale) {
+        s.push_str(", ");
+        s.push_str(Text::Stale.tr(cfg.lang));
+    }
+    s
 }
 
 pub fn src_table(
     src: Source,
     rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
     rate_type: RateType,
     cfg: &config::Gen,
 ) -> String {
-    let Some(rates) = rates.get(&src) else {
-        return "".into();
+    src_rates(src, rates, status, rate_type).render(Format::Text, cfg.rate_dp)
+}
+
+/// Rows of `src_table`.
+pub fn src_rates(
+    src: Source,
+    rates: &HashMap<Source, Vec<Rate>>,
+    status: &HashMap<Source, Status>,
+    rate_type: RateType,
+) -> SrcTable {
+    let rate_type = if src == Source::Cb {
+        RateType::Cb
+    } else {
+        rate_type
     };
+    let rows = rates
+        .get(&src)
+        .into_iter()
+        .flatten()
+        .filter(|v| v.rate_type == rate_type)
+        .map(|rate| SrcRow {
+            from: rate.from.clone(),
+            to: rate.to.clone(),
+            buy: rate.buy,
+            sell: rate.sell,
+        })
+        .collect();
+    SrcTable {
+        src,
+        rate_type,
+        updated_at: status.get(&src).and_then(|v| v.updated_at),
+        rows,
+    }
+}
 
+/// Daily closes of the source, or without one the best buy and sell of the
+/// day across sources.
+pub fn history_table(src: Option<Source>, history: &History, cfg: &config::Gen) -> String {
     #[derive(Debug)]
     struct Row {
+        date: String,
         buy_str: String,
         sell_str: String,
-        from: Currency,
-        to: Currency,
     }
 
-    let mut rate_type = rate_type;
-    if src == Source::Cb {
-        rate_type = RateType::Cb;
-    }
-    let mut table = vec![];
-    let mut buy_width: usize = 0;
-    let mut sell_width: usize = 0;
     const NO_RATE: &str = "-";
-    for rate in rates.iter().filter(|v| v.rate_type == rate_type) {
-        let buy_str = match rate.buy {
-            Some(buy) => decimal_to_string(buy, cfg.rate_dp),
-            _ => NO_RATE.into(),
-        };
-        let sell_str = match rate.sell {
-            Some(sell) => decimal_to_string(sell, cfg.rate_dp),
-            _ => NO_RATE.into(),
-        };
-        let row = Row {
-            buy_str,
-            sell_str,
-            from: rate.from.clone(),
-            to: rate.to.clone(),
+    let cell = |value: Option<(Decimal, Source)>| match value {
+        Some((v, _)) if src.is_some() => decimal_to_string(v, cfg.rate_dp),
+        Some((v, src)) => format!(
+            "{} {}",
+            decimal_to_string(v, cfg.rate_dp),
+            src.to_string().to_lowercase()
+        ),
+        None => NO_RATE.into(),
+    };
+    let mut table = vec![];
+    for (date, day) in history {
+        let (buy, sell) = match src {
+            Some(src) => {
+                let Some(v) = day.get(&src) else {
+                    continue;
+                };
+                (
+                    v.buy.map(|v| (v.close, src)),
+                    v.sell.map(|v| (v.close, src)),
+                )
+            }
+            None => {
+                let day = day
+                    .iter()
+                    .filter(|(src, _)| cfg.show.contains(src.prefix()))
+                    .collect::<Vec<_>>();
+                (
+                    day.iter()
+                        .filter_map(|(src, v)| Some((v.buy?.high, **src)))
+                        .max_by(|a, b| a.0.cmp(&b.0)),
+                    day.iter()
+                        .filter_map(|(src, v)| Some((v.sell?.low, **src)))
+                        .min_by(|a, b| a.0.cmp(&b.0)),
+                )
+            }
         };
-        buy_width = buy_width.max(row.buy_str.len());
-        sell_width = sell_width.max(row.sell_str.len());
-        table.push(row);
+        if buy.is_none() && sell.is_none() {
+            continue;
+        }
+        table.push(Row {
+            date: date.format("%m-%d").to_string(),
+            buy_str: cell(buy),
+            sell_str: cell(sell),
+        });
     }
+    let buy_width = table.iter().map(|v| v.buy_str.len()).max().unwrap_or(0);
     let mut s = String::new();
     for row in table {
         writeln!(
             &mut s,
-            "{:<buy_width$} | {:<sell_width$} | {}/{}",
-            row.buy_str, row.sell_str, row.from, row.to,
+            "{} | {:<buy_width$} | {}",
+            row.date, row.buy_str, row.sell_str,
+        )
+        .unwrap();
+    }
+    s
+}
+
+pub fn status_table(status: &HashMap<Source, Status>, cfg: &Config, gen: &config::Gen) -> String {
+    #[derive(Debug)]
+    struct Row {
+        src: String,
+        state: String,
+        age: String,
+        failures: String,
+        latency: String,
+        rate_count: String,
+        rejected: String,
+        error: String,
+    }
+
+    const NO_VALUE: &str = "-";
+    const ERROR_WIDTH: usize = 32;
+    let lang = gen.lang;
+    let mut table = vec![Row {
+        src: Text::Src.tr(lang).into(),
+        state: Text::State.tr(lang).into(),
+        age: Text::Age.tr(lang).into(),
+        failures: Text::Fails.tr(lang).into(),
+        latency: Text::Ms.tr(lang).into(),
+        rate_count: Text::Rates.tr(lang).into(),
+        rejected: Text::Rejected.tr(lang).into(),
+        error: Text::Error.tr(lang).into(),
+    }];
+    for src in Source::iter() {
+        let default = Status::default();
+        let v = status.get(&src).unwrap_or(&default);
+        let state = if !cfg.src.is_enabled_for(src) {
+            Text::Off.tr(lang)
+        } else if collector::is_paused(v, &cfg.bot) {
+            Text::Pause.tr(lang)
+        } else if v.failures > 0 {
+            Text::Fail.tr(lang)
+        } else if v.stale || get_age(src, status).is_some_and(|v| v.as_secs() > gen.max_age) {
+            Text::Stale.tr(lang)
+        } else if v.updated_at.is_some() {
+            Text::Ok.tr(lang)
+        } else {
+            NO_VALUE
+        };
+        table.push(Row {
+            src: src.to_string().to_lowercase(),
+            state: state.into(),
+            age: get_age(src, status)
+                .map(duration_to_string)
+                .unwrap_or(NO_VALUE.into()),
+            failures: v.failures.to_string(),
+            latency: v
+                .latency
+                .map(|v| v.as_millis().to_string())
+                .unwrap_or(NO_VALUE.into()),
+            rate_count: v.rate_count.to_string(),
+            rejected: v.rejected.to_string(),
+            error: match &v.last_error {
+                Some(err) if v.failures > 0 => err.chars().take(ERROR_WIDTH).collect(),
+                _ => "".into(),
+            },
+        });
+    }
+    let width = |f: fn(&Row) -> &String| {
+        table
+            .iter()
+            .map(|v| f(v).chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let src_width = width(|v| &v.src);
+    let state_width = width(|v| &v.state);
+    let age_width = width(|v| &v.age);
+    let failures_width = width(|v| &v.failures);
+    let latency_width = width(|v| &v.latency);
+    let rate_count_width = width(|v| &v.rate_count);
+    let rejected_width = width(|v| &v.rejected);
+    let mut s = String::new();
+    for row in &table {
+        writeln!(
+            &mut s,
+            "{:<src_width$} | {:<state_width$} | {:>age_width$} | {:>failures_width$} | {:>latency_width$} | {:>rate_count_width$} | {:>rejected_width$} | {}",
+            row.src, row.state, row.age, row.failures, row.latency, row.rate_count, row.rejected, row.error,
         )
         .unwrap();
     }
@@ -197,23 +601,17 @@ pub fn src_table(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{collector, config::Config};
+    use crate::{alert::Op, i18n::Lang};
+    use rust_decimal_macros::dec;
     use std::{
-        sync::{Arc, LazyLock},
-        time::Duration,
+        sync::LazyLock,
+        time::{Duration, SystemTime},
     };
-    use strum::{EnumCount, IntoEnumIterator};
-    use tokio::sync::mpsc;
+    use strum::IntoEnumIterator;
 
     static CFG: LazyLock<Config> =
         LazyLock::new(|| toml::from_str(include_str!("../config/config.toml")).unwrap());
 
-    fn build_client(cfg: &Config) -> reqwest::Result<reqwest::Client> {
-        reqwest::ClientBuilder::new()
-            .timeout(Duration::from_secs(cfg.bot.reqwest_timeout))
-            .build()
-    }
-
     fn get_conversations() -> Vec<(Currency, Currency)> {
         vec![
             (Currency::default(), Currency::usd()),
@@ -226,39 +624,468 @@ mod tests {
     }
 
     async fn collect() -> anyhow::Result<HashMap<Source, Vec<Rate>>> {
-        let client = build_client(&CFG)?;
-        let mut result = HashMap::new();
-        let (tx, mut rx) = mpsc::channel(Source::COUNT);
-        let client = client.clone();
-        let cfg = Arc::new(CFG.clone());
-        {
-            let tx = tx.clone();
-            tokio::spawn(async move {
-                collector::collect(&client, cfg, tx).await;
-            });
-        }
-        drop(tx);
-        while let Some((src, rates)) = rx.recv().await {
-            result.insert(src, rates);
-        }
-        Ok(result)
+        let db = collector::run_once(CFG.clone()).await?;
+        Ok(db.get_rates().await)
     }
 
     #[tokio::test]
     async fn test_conv_table() -> anyhow::Result<()> {
         let rates = collect().await?;
         for (from, to) in &get_conversations() {
-            let _ = conv_table(from, to, &rates, RateType::NoCash, false, &CFG.gen);
-            let _ = conv_table(to, from, &rates, RateType::NoCash, true, &CFG.gen);
+            let _ = conv_table(
+                from,
+                to,
+                None,
+                &rates,
+                &HashMap::new(),
+                RateType::NoCash,
+                false,
+                &CFG,
+                &CFG.gen,
+            );
+            let _ = conv_table(
+                to,
+                from,
+                None,
+                &rates,
+                &HashMap::new(),
+                RateType::NoCash,
+                true,
+                &CFG,
+                &CFG.gen,
+            );
         }
         Ok(())
     }
 
+    #[test]
+    fn test_conv_table_amount() {
+        let rate = |from: Currency, rate_type, buy, sell| Rate {
+            from,
+            to: Currency::default(),
+            rate_type,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let rates = HashMap::from([
+            (
+                Source::Acba,
+                vec![rate(
+                    Currency::usd(),
+                    RateType::NoCash,
+                    dec!(386),
+                    dec!(390),
+                )],
+            ),
+            (
+                Source::Ameria,
+                vec![rate(
+                    Currency::usd(),
+                    RateType::NoCash,
+                    dec!(387),
+                    dec!(389),
+                )],
+            ),
+            (
+                Source::Cb,
+                vec![rate(Currency::usd(), RateType::Cb, dec!(388), dec!(388))],
+            ),
+        ]);
+        let table = |from: Currency, to: Currency, amount| {
+            conv_table(
+                &from,
+                &to,
+                Some(amount),
+                &rates,
+                &HashMap::new(),
+                RateType::NoCash,
+                false,
+                &CFG,
+                &CFG.gen,
+            )
+        };
+        let s = table(Currency::usd(), Currency::default(), dec!(100));
+        let lines = s.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("| 38800 |") && lines[0].contains("| -100 |"));
+        assert!(lines[1].contains("| 38700 |") && lines[1].contains("|    0 |"));
+        assert!(lines[2].contains("| 38600 |") && lines[2].contains("|  100 |"));
+        let s = table(Currency::default(), Currency::usd(), dec!(38900));
+        let lines = s.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains("| 100.26 |") && lines[0].contains("|  -100 |"));
+        assert!(lines[1].contains("| 100    |") && lines[1].contains("|     0 |"));
+        assert!(lines[2].contains("| 99.74  |") && lines[2].contains("| 99.49 |"));
+    }
+
+    #[test]
+    fn test_conv_rates_stale() {
+        let rates = HashMap::from([(
+            Source::Acba,
+            vec![Rate {
+                from: Currency::usd(),
+                to: Currency::default(),
+                rate_type: RateType::NoCash,
+                buy: Some(dec!(386)),
+                sell: Some(dec!(390)),
+            }],
+        )]);
+        let status = |stale| {
+            HashMap::from([(
+                Source::Acba,
+                Status {
+                    updated_at: Some(SystemTime::now() - Duration::from_secs(CFG.gen.max_age + 60)),
+                    stale,
+                    ..Default::default()
+                },
+            )])
+        };
+        let table = |status| {
+            conv_rates(
+                &Currency::usd(),
+                &Currency::default(),
+                None,
+                &rates,
+                &status,
+                RateType::NoCash,
+                false,
+                &CFG,
+                &CFG.gen,
+            )
+        };
+        assert!(table(status(false)).rows.is_empty());
+        let table = table(status(true));
+        assert_eq!(table.rows.len(), 1);
+        assert_eq!(table.rows[0].src, Source::Acba);
+    }
+
+    #[test]
+    fn test_route_table() {
+        let rate = |from: Currency, buy, sell| Rate {
+            from,
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let rates = HashMap::from([
+            (Source::Mir, vec![rate(Currency::rub(), dec!(4.3), dec!(5))]),
+            (
+                Source::Ameria,
+                vec![
+                    rate(Currency::rub(), dec!(4.1), dec!(4.5)),
+                    rate(Currency::usd(), dec!(386), dec!(387)),
+                ],
+            ),
+        ]);
+        let table = |amount| {
+            route_table(
+                &Currency::rub(),
+                &Currency::usd(),
+                amount,
+                &rates,
+                &HashMap::new(),
+                RateType::NoCash,
+                &CFG,
+                &CFG.gen,
+            )
+        };
+        let s = table(None);
+        let lines = s.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "90      | RUB >Mir> AMD >Ameria> USD");
+        assert_eq!(lines[1], "94.3902 | RUB >Ameria> AMD >Ameria> USD");
+        let s = table(Some(dec!(9000)));
+        assert!(s.starts_with("100 "));
+        assert!(route_table(
+            &Currency::rub(),
+            &Currency::rub(),
+            None,
+            &rates,
+            &HashMap::new(),
+            RateType::NoCash,
+            &CFG,
+            &CFG.gen,
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_digest() {
+        let rates = HashMap::from([(
+            Source::Ameria,
+            vec![Rate {
+                from: Currency::usd(),
+                to: Currency::default(),
+                rate_type: RateType::NoCash,
+                buy: Some(dec!(386)),
+                sell: Some(dec!(392)),
+            }],
+        )]);
+        let pairs = &CFG.digest.as_ref().expect("panic").pairs;
+        let sections = digest(pairs, &rates, &HashMap::new(), &CFG, &CFG.gen);
+        assert_eq!(sections.len(), 1);
+        let (title, tables) = &sections[0];
+        assert_eq!(title, "USD/AMD no cash");
+        assert_eq!(tables.len(), 2);
+        assert!(tables[0].contains("| 386 "));
+        assert!(tables[1].contains("| 392 "));
+    }
+
+    #[test]
+    fn test_conv_tables() {
+        let rates = HashMap::from([(
+            Source::Ameria,
+            vec![Rate {
+                from: Currency::usd(),
+                to: Currency::default(),
+                rate_type: RateType::NoCash,
+                buy: None,
+                sell: Some(dec!(392)),
+            }],
+        )]);
+        let tables = conv_tables(
+            &Currency::usd(),
+            &Currency::default(),
+            &rates,
+            &HashMap::new(),
+            RateType::NoCash,
+            &CFG,
+            &CFG.gen,
+        );
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].0, Currency::default());
+        assert_eq!(tables[0].1, Currency::usd());
+        assert!(tables[0].2.contains("| 392 "));
+    }
+
+    #[test]
+    fn test_best_rate() {
+        let rate = |from: Currency, buy, sell| Rate {
+            from,
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let rates = HashMap::from([
+            (Source::Mir, vec![rate(Currency::rub(), dec!(4.6), dec!(5))]),
+            (
+                Source::Acba,
+                vec![rate(Currency::rub(), dec!(4.2), dec!(4.6))],
+            ),
+            (
+                Source::Ameria,
+                vec![rate(Currency::rub(), dec!(4.3), dec!(4.5))],
+            ),
+        ]);
+        let best = |from: Currency, to: Currency| {
+            best_rate(
+                &from,
+                &to,
+                &rates,
+                &HashMap::new(),
+                RateType::NoCash,
+                &CFG,
+                &CFG.gen,
+            )
+        };
+        assert_eq!(
+            best(Currency::rub(), Currency::default()),
+            Some((Source::Ameria, dec!(4.3)))
+        );
+        assert_eq!(
+            best(Currency::default(), Currency::rub()),
+            Some((Source::Ameria, Decimal::ONE / dec!(4.5)))
+        );
+        assert_eq!(best(Currency::usd(), Currency::default()), None);
+        // restored rates and hidden banks do not decide alerts
+        let status = HashMap::from([(
+            Source::Ameria,
+            Status {
+                updated_at: Some(SystemTime::now()),
+                stale: true,
+                ..Default::default()
+            },
+        )]);
+        let best = |gen: &config::Gen| {
+            best_rate(
+                &Currency::rub(),
+                &Currency::default(),
+                &rates,
+                &status,
+                RateType::NoCash,
+                &CFG,
+                gen,
+            )
+        };
+        assert_eq!(best(&CFG.gen), Some((Source::Acba, dec!(4.2))));
+        let hidden = config::Gen {
+            show: "#@".into(),
+            ..CFG.gen.clone()
+        };
+        assert_eq!(best(&hidden), None);
+    }
+
+    #[test]
+    fn test_arbitrage_table() {
+        let rate = |from: Currency, buy, sell| Rate {
+            from,
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let mut rates = HashMap::from([
+            (Source::Mir, vec![rate(Currency::rub(), dec!(4.3), dec!(5))]),
+            (
+                Source::Ameria,
+                vec![rate(Currency::rub(), dec!(4.1), dec!(4.5))],
+            ),
+        ]);
+        let table = |rates: &HashMap<Source, Vec<Rate>>| {
+            arbitrage_table(rates, &HashMap::new(), RateType::NoCash, &CFG, &CFG.gen)
+        };
+        assert!(table(&rates).is_empty());
+        rates.insert(Source::Mir, vec![rate(Currency::rub(), dec!(4.6), dec!(5))]);
+        assert_eq!(
+            table(&rates),
+            "all: AMD >Ameria 0.2222> RUB >Mir 4.6> AMD +2.22%\n"
+        );
+    }
+
+    #[test]
+    fn test_history_table() {
+        let rate = |buy, sell| Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell: Some(sell),
+        };
+        let ts = |s: &str| {
+            std::time::SystemTime::from(chrono::DateTime::parse_from_rfc3339(s).expect("panic"))
+        };
+        let history = crate::database::aggregate(
+            &[
+                (
+                    Source::Acba,
+                    ts("2024-03-01T08:00:00Z"),
+                    rate(dec!(386), dec!(391)),
+                ),
+                (
+                    Source::Acba,
+                    ts("2024-03-01T12:00:00Z"),
+                    rate(dec!(385), dec!(390)),
+                ),
+                (
+                    Source::Ameria,
+                    ts("2024-03-01T12:00:00Z"),
+                    rate(dec!(387), dec!(392)),
+                ),
+                (
+                    Source::Acba,
+                    ts("2024-03-02T08:00:00Z"),
+                    rate(dec!(384.5), dec!(389)),
+                ),
+            ],
+            CFG.gen.utc_offset,
+        );
+        let s = history_table(None, &history, &CFG.gen);
+        let lines = s.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "03-01 | 387 ameria | 390 acba");
+        assert_eq!(lines[1], "03-02 | 384.5 acba | 389 acba");
+        let s = history_table(Some(Source::Acba), &history, &CFG.gen);
+        assert_eq!(s, "03-01 | 385   | 390\n03-02 | 384.5 | 389\n");
+        assert!(history_table(Some(Source::Vtb), &history, &CFG.gen).is_empty());
+    }
+
+    #[test]
+    fn test_duration_to_string() {
+        assert_eq!(duration_to_string(Duration::from_secs(59)), "0m");
+        assert_eq!(duration_to_string(Duration::from_secs(3 * 60 + 5)), "3m");
+        assert_eq!(
+            duration_to_string(Duration::from_secs(2 * 3600 + 60)),
+            "2h 1m"
+        );
+        assert_eq!(
+            duration_to_string(Duration::from_secs(86400 + 3600)),
+            "1d 1h"
+        );
+    }
+
+    #[test]
+    fn test_status_table() {
+        let mut status = HashMap::new();
+        status.insert(
+            Source::Acba,
+            Status {
+                updated_at: Some(std::time::SystemTime::now()),
+                rate_count: 10,
+                ..Default::default()
+            },
+        );
+        status.insert(
+            Source::Vtb,
+            Status {
+                failures: 3,
+                last_error: Some("error sending request".into()),
+                ..Default::default()
+            },
+        );
+        let s = status_table(&status, &CFG, &CFG.gen);
+        let lines = s.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), Source::iter().count() + 1);
+        assert!(lines
+            .iter()
+            .any(|v| v.starts_with("acba ") && v.contains("| ok ")));
+        assert!(lines
+            .iter()
+            .any(|v| v.starts_with("vtb ") && v.contains("error sending request")));
+        let gen = config::Gen {
+            lang: Lang::Ru,
+            ..CFG.gen.clone()
+        };
+        let s = status_table(&status, &CFG, &gen);
+        assert!(s
+            .lines()
+            .any(|v| v.starts_with("acba ") && v.contains("| ок ")));
+        assert!(s
+            .lines()
+            .any(|v| v.starts_with("vtb ") && v.contains("| сбой ")));
+    }
+
+    #[test]
+    fn test_alert_note() {
+        let alert = Alert {
+            id: 1,
+            chat_id: 1,
+            from: Currency::usd(),
+            to: Currency::default(),
+            op: Op::Above,
+            value: dec!(400),
+            rate_type: RateType::Cash,
+            triggered: false,
+        };
+        assert_eq!(
+            alert_note(&alert, Source::Acba, dec!(401.5), &CFG.gen),
+            "alert #1 USD/AMD > 400 cash: 401.5 acba"
+        );
+        let gen = config::Gen {
+            lang: Lang::Hy,
+            ..CFG.gen.clone()
+        };
+        assert_eq!(
+            alert_note(&alert, Source::Acba, dec!(401.5), &gen),
+            "ծանուցում #1 USD/AMD > 400 կանխիկ: 401.5 acba"
+        );
+    }
+
     #[tokio::test]
     async fn test_src_table() -> anyhow::Result<()> {
         let rates = collect().await?;
         for src in Source::iter() {
-            let _ = src_table(src, &rates, RateType::NoCash, &CFG.gen);
+            let _ = src_table(src, &rates, &HashMap::new(), RateType::NoCash, &CFG.gen);
         }
         Ok(())
     }
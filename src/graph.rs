@@ -1,17 +1,97 @@
-use crate::source::{Currency, Rate, RateType};
+use crate::source::{self, Currency, Fee, Rate, RateType, Source};
 use rust_decimal::{prelude::ToPrimitive, Decimal};
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, iter,
+};
+
+pub type Graph = HashMap<Currency, Vec<Edge>>;
 
 #[derive(Debug, Clone)]
 pub struct Edge {
     to: Currency,
     rate: Decimal,
+    src: Source,
+    fees: Vec<Fee>,
+}
+
+/// Conversion path with the source of every step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub currencies: Vec<Currency>,
+    pub srcs: Vec<Source>,
+    pub rate: Decimal,
+}
+
+impl Path {
+    /// Number of distinct sources used.
+    pub fn src_count(&self) -> usize {
+        self.srcs.iter().collect::<HashSet<_>>().len()
+    }
+}
+
+/// Bounds for the path search.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub hops: usize,
+    pub srcs: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            hops: usize::MAX,
+            srcs: usize::MAX,
+        }
+    }
+}
+
+pub fn build(src: Source, rates: &[Rate], rate_type: RateType, fees: &[Fee]) -> Graph {
+    let mut graph = Graph::new();
+    extend(&mut graph, src, rates, rate_type, fees);
+    graph
 }
 
-pub fn build(rates: &[Rate], rate_type: RateType) -> HashMap<Currency, Vec<Edge>> {
-    let mut graph: HashMap<Currency, Vec<Edge>> = HashMap::new();
+/// Combined graph of every source except the central bank, whose rates can
+/// not be traded.
+pub fn build_all(
+    rates: &HashMap<Source, Vec<Rate>>,
+    rate_type: RateType,
+    cfg: &source::Config,
+) -> Graph {
+    let mut graph = Graph::new();
+    for (src, rates) in rates.iter().filter(|(src, _)| **src != Source::Cb) {
+        extend(
+            &mut graph,
+            *src,
+            rates,
+            rate_type,
+            &cfg.options_for(*src).fees,
+        );
+    }
+    graph
+}
+
+/// Adds the edges of `src`, a pair quoted more than once keeps its best rate
+/// as the fees of a source only depend on the pair.
+fn extend(graph: &mut Graph, src: Source, rates: &[Rate], rate_type: RateType, fees: &[Fee]) {
     let mut add_edge = |from: Currency, to: Currency, rate: Decimal| {
-        graph.entry(from).or_default().push(Edge { to, rate });
+        let edges = graph.entry(from.clone()).or_default();
+        if let Some(edge) = edges.iter_mut().find(|v| v.to == to && v.src == src) {
+            edge.rate = edge.rate.max(rate);
+            return;
+        }
+        let fees = fees
+            .iter()
+            .filter(|v| v.is_for(&from, &to))
+            .cloned()
+            .collect();
+        edges.push(Edge {
+            to,
+            rate,
+            src,
+            fees,
+        });
     };
     for rate in rates
         .iter()
@@ -28,314 +108,230 @@ pub fn build(rates: &[Rate], rate_type: RateType) -> HashMap<Currency, Vec<Edge>
             }
         }
     }
-    graph
 }
 
+/// All paths from `from` to `to` within `limits` with their effective rates
+/// after fees, for `amount` of `from` if given.
 pub fn find_all_paths(
-    graph: &HashMap<Currency, Vec<Edge>>,
+    graph: &Graph,
     from: &Currency,
     to: &Currency,
-) -> Vec<(Vec<Currency>, Decimal)> {
-    let mut paths = Vec::new();
-    let mut path = Vec::new();
-    let mut visited = HashSet::new();
-    dfs(
+    amount: Option<Decimal>,
+    limits: Limits,
+) -> Vec<Path> {
+    let start = amount.unwrap_or(Decimal::ONE);
+    let mut search = Search {
         graph,
-        from,
         to,
-        &mut visited,
-        &mut path,
-        &mut paths,
-        Decimal::ONE,
-    );
-    paths
+        is_amount: amount.is_some(),
+        limits,
+        visited: HashSet::new(),
+        path: Vec::new(),
+        srcs: Vec::new(),
+        paths: Vec::new(),
+    };
+    search.dfs(from, start);
+    for path in search.paths.iter_mut() {
+        path.rate /= start;
+    }
+    search.paths
 }
 
-fn dfs(
-    graph: &HashMap<Currency, Vec<Edge>>,
-    from: &Currency,
-    to: &Currency,
-    visited: &mut HashSet<Currency>,
-    path: &mut Vec<Currency>,
-    paths: &mut Vec<(Vec<Currency>, Decimal)>,
-    rate: Decimal,
-) {
-    visited.insert(from.clone());
-    path.push(from.clone());
-    if from == to {
-        paths.push((path.clone(), rate));
-    } else {
-        if let Some(edges) = graph.get(&from) {
-            for edge in edges {
-                if visited.contains(&edge.to) {
+struct Search<'a> {
+    graph: &'a Graph,
+    to: &'a Currency,
+    is_amount: bool,
+    limits: Limits,
+    visited: HashSet<Currency>,
+    path: Vec<Currency>,
+    srcs: Vec<Source>,
+    paths: Vec<Path>,
+}
+
+impl Search<'_> {
+    fn dfs(&mut self, from: &Currency, value: Decimal) {
+        self.visited.insert(from.clone());
+        self.path.push(from.clone());
+        if from == self.to {
+            self.paths.push(Path {
+                currencies: self.path.clone(),
+                srcs: self.srcs.clone(),
+                rate: value,
+            });
+        } else if self.srcs.len() < self.limits.hops {
+            let graph = self.graph;
+            let src_count = self.srcs.iter().collect::<HashSet<_>>().len();
+            for edge in graph.get(from).into_iter().flatten() {
+                if self.visited.contains(&edge.to) {
                     continue;
                 }
-                let new_rate = rate * edge.rate;
-                dfs(graph, &edge.to, to, visited, path, paths, new_rate);
+                if src_count >= self.limits.srcs && !self.srcs.contains(&edge.src) {
+                    continue;
+                }
+                let Some(fee) = get_fee(graph, from, edge, value, self.is_amount) else {
+                    continue;
+                };
+                let new_value = (value - fee) * edge.rate;
+                if new_value <= Decimal::ZERO {
+                    continue;
+                }
+                self.srcs.push(edge.src);
+                self.dfs(&edge.to, new_value);
+                self.srcs.pop();
             }
         }
+        self.path.pop();
+        self.visited.remove(from);
     }
-    path.pop();
-    visited.remove(&from);
 }
 
-pub fn detect_arbitrage(rates: &[Rate], rate_type: RateType) -> bool {
-    let rates = rates
-        .iter()
-        .filter(|v| v.rate_type == rate_type)
-        .cloned()
-        .collect::<Vec<_>>();
-    let currency_indices = {
-        let mut map = HashMap::new();
-        let mut idx = 0;
-        for rate in &rates {
-            if !map.contains_key(&rate.from) {
-                map.insert(rate.from.clone(), idx);
-                idx += 1;
-            }
-            if !map.contains_key(&rate.to) {
-                map.insert(rate.to.clone(), idx);
-                idx += 1;
-            }
+/// Fee in `from` charged on the edge for `value` of `from`, the tier with the
+/// highest `above` wins. `None` if the fee currency can not be converted.
+fn get_fee(
+    graph: &Graph,
+    from: &Currency,
+    edge: &Edge,
+    value: Decimal,
+    is_amount: bool,
+) -> Option<Decimal> {
+    let mut result: Option<(Decimal, Decimal)> = None;
+    for fee in &edge.fees {
+        if !is_amount && !fee.above.is_zero() {
+            continue;
         }
-        map
-    };
-    if currency_indices.is_empty() {
-        return false;
+        let rate = match &fee.currency {
+            Some(cur) if cur != from && is_amount => graph
+                .get(from)?
+                .iter()
+                .find(|v| &v.to == cur && v.src == edge.src)
+                .map(|v| v.rate)?,
+            _ => Decimal::ONE,
+        };
+        let amount = value * rate;
+        if amount < fee.above || result.is_some_and(|(above, _)| above > fee.above) {
+            continue;
+        }
+        result = Some((fee.above, fee.charge(amount, is_amount) / rate));
     }
-    let num_currencies = currency_indices.len();
-    let mut dist = vec![f64::INFINITY; num_currencies];
-    dist[0] = 0.0;
-    let mut edges = Vec::new();
-    for rate in &rates {
-        let from = currency_indices.get(&rate.from).unwrap();
-        let to = currency_indices.get(&rate.to).unwrap();
-        if let Some(buy) = rate.buy {
-            if buy > Decimal::ZERO {
-                let weight = -buy.to_f64().unwrap().ln();
-                edges.push((*from, *to, weight));
-            }
+    Some(result.map(|v| v.1).unwrap_or_default())
+}
+
+/// Profitable currency cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arbitrage {
+    /// Currencies of the cycle, the first one repeated at the end.
+    pub currencies: Vec<Currency>,
+    pub srcs: Vec<Source>,
+    /// Rate of every step after fees.
+    pub rates: Vec<Decimal>,
+    /// Gain of one round in percent.
+    pub gain: Decimal,
+}
+
+impl fmt::Display for Arbitrage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.currencies[0])?;
+        for ((src, rate), cur) in self.srcs.iter().zip(&self.rates).zip(&self.currencies[1..]) {
+            write!(f, " >{src} {}> {cur}", rate.round_dp(4).normalize())?;
         }
-        if let Some(sell) = rate.sell {
-            if sell > Decimal::ZERO {
-                let weight = -(Decimal::ONE / sell).to_f64().unwrap().ln();
-                edges.push((*to, *from, weight));
-            }
+        write!(f, " +{}%", self.gain.round_dp(2).normalize())
+    }
+}
+
+/// Finds a negative cycle of `-ln(rate)` with Bellman-Ford and returns it if
+/// the exact `Decimal` product of its rates is above one.
+pub fn find_arbitrage(graph: &Graph) -> Option<Arbitrage> {
+    let mut currencies = graph
+        .iter()
+        .flat_map(|(from, edges)| iter::once(from).chain(edges.iter().map(|v| &v.to)))
+        .collect::<Vec<_>>();
+    currencies.sort_by(|a, b| a.0.cmp(&b.0));
+    currencies.dedup();
+    let indices = currencies
+        .iter()
+        .enumerate()
+        .map(|(idx, v)| (*v, idx))
+        .collect::<HashMap<_, _>>();
+    let mut edges = vec![];
+    for (from, es) in graph {
+        for edge in es {
+            let fee = get_fee(graph, from, edge, Decimal::ONE, false).unwrap_or_default();
+            let rate = (Decimal::ONE - fee) * edge.rate;
+            let Some(weight) = rate.to_f64().filter(|v| *v > 0.0).map(|v| -v.ln()) else {
+                continue;
+            };
+            edges.push((indices[from], indices[&edge.to], weight, rate, edge.src));
         }
     }
     const EPSILON: f64 = 1e-8;
-    for _ in 0..num_currencies - 1 {
-        for &(u, v, weight) in &edges {
+    let num_currencies = currencies.len();
+    let mut dist = vec![0.0; num_currencies];
+    let mut pred = vec![None; num_currencies];
+    let mut last = None;
+    for _ in 0..num_currencies {
+        last = None;
+        for (idx, &(u, v, weight, ..)) in edges.iter().enumerate() {
             if dist[u] + weight < dist[v] - EPSILON {
                 dist[v] = dist[u] + weight;
+                pred[v] = Some(idx);
+                last = Some(v);
             }
         }
     }
-    for &(u, v, weight) in &edges {
-        if dist[u] + weight < dist[v] - EPSILON {
-            return true;
+    let mut v = last?;
+    for _ in 0..num_currencies {
+        v = edges[pred[v]?].0;
+    }
+    let mut cycle = vec![];
+    let mut cur = v;
+    loop {
+        let idx = pred[cur]?;
+        cycle.push(idx);
+        cur = edges[idx].0;
+        if cur == v {
+            break;
+        }
+        if cycle.len() > num_currencies {
+            return None;
         }
     }
-    false
+    cycle.reverse();
+    if let Some(pos) = cycle
+        .iter()
+        .position(|v| currencies[edges[*v].0] == &Currency::default())
+    {
+        cycle.rotate_left(pos);
+    }
+    let rates = cycle.iter().map(|v| edges[*v].3).collect::<Vec<_>>();
+    let product = rates.iter().fold(Decimal::ONE, |acc, v| acc * v);
+    if product <= Decimal::ONE {
+        return None;
+    }
+    let mut path = vec![currencies[edges[cycle[0]].0].clone()];
+    path.extend(cycle.iter().map(|v| currencies[edges[*v].1].clone()));
+    Some(Arbitrage {
+        currencies: path,
+        srcs: cycle.iter().map(|v| edges[*v].4).collect(),
+        rates,
+        gain: (product - Decimal::ONE) * Decimal::ONE_HUNDRED,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::source::acba;
+    use crate::{config::Config, source::acba};
     use rust_decimal_macros::dec;
+    use std::{
+        sync::LazyLock,
+        time::{Duration, Instant},
+    };
+    use strum::IntoEnumIterator;
 
-    const ACBA_DATA: &str = r#"{
-      "Description": null,
-      "ResultCode": 1,
-      "Result": {
-        "rates": {
-          "last_update_date": "2024-09-16T16:07:00+04:00",
-          "cash": [
-            {
-              "Buy": "384.5",
-              "Sell": "390.5",
-              "CB": "387.19",
-              "Currency": "USD"
-            },
-            {
-              "Buy": "425",
-              "Sell": "437",
-              "CB": "430.63",
-              "Currency": "EUR"
-            },
-            {
-              "Buy": "4.21",
-              "Sell": "4.44",
-              "CB": "4.25",
-              "Currency": "RUR"
-            },
-            {
-              "Buy": "499",
-              "Sell": "519",
-              "CB": "510.82",
-              "Currency": "GBP"
-            },
-            {
-              "Buy": "448",
-              "Sell": "468",
-              "CB": "458.38",
-              "Currency": "CHF"
-            },
-            {
-              "Buy": "140",
-              "Sell": "160",
-              "CB": "143.75",
-              "Currency": "GEL"
-            }
-          ],
-          "non_cash": [
-            {
-              "Buy": "384",
-              "Sell": "390",
-              "CB": "387.19",
-              "Currency": "USD"
-            },
-            {
-              "Buy": "425",
-              "Sell": "437",
-              "CB": "430.63",
-              "Currency": "EUR"
-            },
-            {
-              "Buy": "4.21",
-              "Sell": "4.44",
-              "CB": "4.25",
-              "Currency": "RUR"
-            },
-            {
-              "Buy": "499",
-              "Sell": "519",
-              "CB": "510.82",
-              "Currency": "GBP"
-            },
-            {
-              "Buy": "448",
-              "Sell": "468",
-              "CB": "458.38",
-              "Currency": "CHF"
-            },
-            {
-              "Buy": "140",
-              "Sell": "160",
-              "CB": "143.75",
-              "Currency": "GEL"
-            }
-          ],
-          "card": [
-            {
-              "Buy": "384",
-              "Sell": "390.5",
-              "CB": "387.19",
-              "Currency": "USD"
-            },
-            {
-              "Buy": "425",
-              "Sell": "437",
-              "CB": "430.63",
-              "Currency": "EUR"
-            },
-            {
-              "Buy": "4.21",
-              "Sell": "4.44",
-              "CB": "4.25",
-              "Currency": "RUR"
-            },
-            {
-              "Buy": "499",
-              "Sell": "519",
-              "CB": "510.82",
-              "Currency": "GBP"
-            },
-            {
-              "Buy": "448",
-              "Sell": "468",
-              "CB": "458.38",
-              "Currency": "CHF"
-            },
-            {
-              "Buy": "140",
-              "Sell": "160",
-              "CB": "143.75",
-              "Currency": "GEL"
-            }
-          ],
-          "cross": [
-            {
-              "Buy": "1.0883483",
-              "Sell": "1.1380208",
-              "Currency": "EUR/USD"
-            },
-            {
-              "Buy": "86.4864865",
-              "Sell": "92.7553444",
-              "Currency": "USD/RUR"
-            },
-            {
-              "Buy": "1.2778489",
-              "Sell": "1.3515625",
-              "Currency": "GBP/USD"
-            },
-            {
-              "Buy": "0.8205128",
-              "Sell": "0.8716518",
-              "Currency": "USD/CHF"
-            },
-            {
-              "Buy": "95.7207207",
-              "Sell": "103.8004751",
-              "Currency": "EUR/RUR"
-            },
-            {
-              "Buy": "0.9081197",
-              "Sell": "0.9754464",
-              "Currency": "EUR/CHF"
-            },
-            {
-              "Buy": "0.8188825",
-              "Sell": "0.8757515",
-              "Currency": "EUR/GBP"
-            }
-          ],
-          "currencies": [
-            {
-              "Key": "AMD",
-              "Value": "AMD"
-            },
-            {
-              "Key": "USD",
-              "Value": "USD"
-            },
-            {
-              "Key": "EUR",
-              "Value": "EUR"
-            },
-            {
-              "Key": "RUR",
-              "Value": "RUR"
-            },
-            {
-              "Key": "GBP",
-              "Value": "GBP"
-            },
-            {
-              "Key": "CHF",
-              "Value": "CHF"
-            },
-            {
-              "Key": "GEL",
-              "Value": "GEL"
-            }
-          ]
-        }
-      },
-      "ResultCodeDescription": "normal"
-    }"#;
+    static CFG: LazyLock<Config> =
+        LazyLock::new(|| toml::from_str(include_str!("../config/config.toml")).unwrap());
+
+    const ACBA_DATA: &str = include_str!("../fixtures/acba.json");
 
     fn get_conversations() -> Vec<(Currency, Currency)> {
         vec![
@@ -352,13 +348,200 @@ mod tests {
     fn test_graph() -> anyhow::Result<()> {
         let resp: acba::Response = serde_json::from_str(ACBA_DATA)?;
         let rates = acba::parse(resp)?;
-        let graph = build(&rates, RateType::NoCash);
+        let graph = build(Source::Acba, &rates, RateType::NoCash, &[]);
         for (from, to) in &get_conversations() {
-            let _ = find_all_paths(&graph, from, to);
+            let paths = find_all_paths(&graph, from, to, None, Limits::default());
+            assert!(!paths.is_empty(), "{from}/{to}");
+            for path in &paths {
+                assert_eq!(path.currencies.first(), Some(from));
+                assert_eq!(path.currencies.last(), Some(to));
+                assert!(path.rate > Decimal::ZERO);
+            }
         }
+        let best = |from: Currency, to: Currency| {
+            find_all_paths(&graph, &from, &to, None, Limits::default())
+                .into_iter()
+                .max_by(|a, b| a.rate.cmp(&b.rate))
+                .expect("panic")
+        };
+        let path = best(Currency::usd(), Currency::default());
+        assert_eq!(path.currencies, [Currency::usd(), Currency::default()]);
+        assert_eq!(path.rate, dec!(384));
+        let path = best(Currency::default(), Currency::usd());
+        assert_eq!(path.currencies, [Currency::default(), Currency::usd()]);
+        assert_eq!(path.rate, Decimal::ONE / dec!(390));
+        let path = best(Currency::rub(), Currency::usd());
+        assert_eq!(
+            path.currencies,
+            [Currency::rub(), Currency::default(), Currency::usd()]
+        );
+        assert_eq!(path.rate, dec!(4.21) * (Decimal::ONE / dec!(390)));
         Ok(())
     }
 
+    #[test]
+    fn test_find_all_paths_limits() {
+        let currencies = ["AMD", "USD", "EUR", "RUB", "GBP", "GEL"].map(Currency::new);
+        let mut rates = vec![];
+        for (i, from) in currencies.iter().enumerate() {
+            for to in &currencies[i + 1..] {
+                for (buy, sell) in [(dec!(2), dec!(3)), (dec!(1), dec!(4))] {
+                    rates.push(Rate {
+                        from: from.clone(),
+                        to: to.clone(),
+                        rate_type: RateType::NoCash,
+                        buy: Some(buy),
+                        sell: Some(sell),
+                    });
+                }
+            }
+        }
+        let rates = Source::iter()
+            .filter(|v| *v != Source::Cb)
+            .map(|v| (v, rates.clone()))
+            .collect::<HashMap<_, _>>();
+        let graph = build_all(&rates, RateType::NoCash, &CFG.src);
+        let limits = Limits {
+            hops: CFG.gen.route_hops,
+            srcs: CFG.gen.route_srcs,
+        };
+        let started_at = Instant::now();
+        let paths = find_all_paths(&graph, &Currency::usd(), &Currency::default(), None, limits);
+        let elapsed = started_at.elapsed();
+        // 1 direct, 4 via one currency and 12 via two, by up to 2 of n sources
+        let n = rates.len();
+        assert_eq!(
+            paths.len(),
+            n + 4 * n.pow(2) + 12 * (n.pow(3) - n * (n - 1) * (n - 2))
+        );
+        // the worse duplicate quotes are dropped
+        assert!(paths
+            .iter()
+            .filter(|v| v.srcs.len() == 1)
+            .all(|v| v.rate == Decimal::ONE / dec!(3)));
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    }
+
+    #[test]
+    fn test_fees() {
+        let rates = vec![Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(dec!(400)),
+            sell: Some(dec!(410)),
+        }];
+        let rate = |fees: &[Fee], amount| {
+            let graph = build(Source::Acba, &rates, RateType::NoCash, fees);
+            find_all_paths(
+                &graph,
+                &Currency::usd(),
+                &Currency::default(),
+                amount,
+                Limits::default(),
+            )[0]
+            .rate
+        };
+        let fee = |percent, fixed| Fee {
+            percent,
+            fixed,
+            ..Default::default()
+        };
+        assert_eq!(rate(&[], None), dec!(400));
+        assert_eq!(rate(&[fee(dec!(1), dec!(5))], None), dec!(396));
+        assert_eq!(rate(&[fee(dec!(1), dec!(5))], Some(dec!(100))), dec!(376));
+        let min = Fee {
+            min: Some(dec!(10)),
+            ..fee(dec!(1), dec!(0))
+        };
+        assert_eq!(rate(&[min], Some(dec!(100))), dec!(360));
+        let tiers = [
+            fee(dec!(2), dec!(0)),
+            Fee {
+                above: dec!(1000),
+                ..fee(dec!(1), dec!(0))
+            },
+        ];
+        assert_eq!(rate(&tiers, None), dec!(392));
+        assert_eq!(rate(&tiers, Some(dec!(100))), dec!(392));
+        assert_eq!(rate(&tiers, Some(dec!(2000))), dec!(396));
+        let amd = Fee {
+            currency: Some(Currency::default()),
+            ..fee(dec!(0), dec!(1000))
+        };
+        assert_eq!(rate(&[amd], Some(dec!(100))), dec!(390));
+        let other = Fee {
+            from: Some(Currency::default()),
+            ..fee(dec!(50), dec!(0))
+        };
+        assert_eq!(rate(&[other], None), dec!(400));
+        assert!(find_all_paths(
+            &build(
+                Source::Acba,
+                &rates,
+                RateType::NoCash,
+                &[fee(dec!(0), dec!(200))]
+            ),
+            &Currency::usd(),
+            &Currency::default(),
+            Some(dec!(100)),
+            Limits::default(),
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_build_all() {
+        let rate = |from: Currency, buy, sell| Rate {
+            from,
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy,
+            sell,
+        };
+        let rates = HashMap::from([
+            (
+                Source::Mir,
+                vec![rate(Currency::rub(), Some(dec!(4.3)), None)],
+            ),
+            (
+                Source::Acba,
+                vec![
+                    rate(Currency::rub(), Some(dec!(4.2)), Some(dec!(4.5))),
+                    rate(Currency::usd(), Some(dec!(386)), Some(dec!(388))),
+                ],
+            ),
+            (
+                Source::Cb,
+                vec![Rate {
+                    rate_type: RateType::Cb,
+                    ..rate(Currency::usd(), Some(dec!(300)), Some(dec!(300)))
+                }],
+            ),
+        ]);
+        let graph = build_all(&rates, RateType::NoCash, &CFG.src);
+        let find = |hops, srcs| {
+            let mut paths = find_all_paths(
+                &graph,
+                &Currency::rub(),
+                &Currency::usd(),
+                None,
+                Limits { hops, srcs },
+            );
+            paths.sort_by_key(|v| std::cmp::Reverse(v.rate));
+            paths
+        };
+        let paths = find(2, 2);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].srcs, vec![Source::Mir, Source::Acba]);
+        assert_eq!(paths[0].src_count(), 2);
+        assert_eq!(paths[0].rate, dec!(4.3) / dec!(388));
+        let paths = find(2, 1);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].srcs, vec![Source::Acba, Source::Acba]);
+        assert!(find(1, 2).is_empty());
+    }
+
     #[test]
     fn test_arbitrage_true_buy() {
         let rates = vec![
@@ -384,7 +567,18 @@ mod tests {
                 sell: Some(dec!(1.5)),
             },
         ];
-        assert!(detect_arbitrage(&rates, RateType::NoCash));
+        let graph = build(Source::Acba, &rates, RateType::NoCash, &[]);
+        let arbitrage = find_arbitrage(&graph).expect("panic");
+        assert_eq!(arbitrage.currencies.len(), 4);
+        assert_eq!(arbitrage.currencies[0], arbitrage.currencies[3]);
+        assert_eq!(arbitrage.srcs, vec![Source::Acba; 3]);
+        assert_eq!(arbitrage.gain, dec!(0.0648));
+        let fees = [Fee {
+            percent: dec!(0.1),
+            ..Default::default()
+        }];
+        let graph = build(Source::Acba, &rates, RateType::NoCash, &fees);
+        assert!(find_arbitrage(&graph).is_none());
     }
 
     #[test]
@@ -412,7 +606,10 @@ mod tests {
                 sell: Some(dec!(1.1)),
             },
         ];
-        assert!(detect_arbitrage(&rates, RateType::NoCash));
+        let graph = build(Source::Acba, &rates, RateType::NoCash, &[]);
+        let arbitrage = find_arbitrage(&graph).expect("panic");
+        assert!(arbitrage.gain > Decimal::ZERO);
+        assert_eq!(arbitrage.rates.len(), 3);
     }
 
     #[test]
@@ -440,6 +637,7 @@ mod tests {
                 sell: Some(dec!(1.4)),
             },
         ];
-        assert!(!detect_arbitrage(&rates, RateType::NoCash));
+        let graph = build(Source::Acba, &rates, RateType::NoCash, &[]);
+        assert!(find_arbitrage(&graph).is_none());
     }
 }
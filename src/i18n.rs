@@ -0,0 +1,265 @@
+//! Translations of the bot replies, command descriptions and table headers.
+
+use crate::source::RateType;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+    strum::EnumString,
+    strum::Display,
+    strum::EnumIter,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Lang {
+    #[default]
+    En,
+    Ru,
+    Hy,
+}
+
+impl Lang {
+    /// Language of a Telegram `language_code` like `ru` or `hy-AM`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.split(['-', '_']).next()? {
+            "en" => Some(Self::En),
+            "ru" => Some(Self::Ru),
+            "hy" => Some(Self::Hy),
+            _ => None,
+        }
+    }
+
+    /// Picks the translation of `[en, ru, hy]`.
+    fn pick(self, values: [&'static str; 3]) -> &'static str {
+        values[self as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Text {
+    Help,
+    Version,
+    UpdateInterval,
+    UpdatedAt,
+    Stale,
+    Updated,
+    MaxAlerts,
+    Deleted,
+    Subscribed,
+    Unsubscribed,
+    Now,
+    NoPairs,
+    Src,
+    State,
+    Age,
+    Fails,
+    Rates,
+    Rejected,
+    Error,
+    Ms,
+    Off,
+    Pause,
+    Fail,
+    Ok,
+    Alert,
+    NoCash,
+    Cash,
+    Card,
+    Online,
+    Cb,
+}
+
+impl Text {
+    pub fn tr(self, lang: Lang) -> &'static str {
+        lang.pick(match self {
+            Self::Help => [
+                "These commands are supported:",
+                "Поддерживаемые команды:",
+                "Աջակցվող հրամաններ՝",
+            ],
+            Self::Version => ["version", "версия", "տարբերակ"],
+            Self::UpdateInterval => [
+                "update_interval",
+                "интервал_обновления",
+                "թարմացման_միջակայք",
+            ],
+            Self::UpdatedAt => ["updated_at", "обновлено", "թարմացվել է"],
+            Self::Stale => ["stale", "устарело", "հնացած"],
+            Self::Updated => [
+                "updated {} ago",
+                "обновлено {} назад",
+                "թարմացվել է {} առաջ",
+            ],
+            Self::MaxAlerts => [
+                "max alerts: {}, /unalert some",
+                "максимум оповещений: {}, удалите лишние /unalert",
+                "առավելագույնը {} ծանուցում, ջնջեք /unalert",
+            ],
+            Self::Deleted => ["deleted", "удалено", "ջնջված է"],
+            Self::Subscribed => [
+                "subscribed, daily at {}",
+                "подписка оформлена, ежедневно в {}",
+                "բաժանորդագրված եք, ամեն օր {}",
+            ],
+            Self::Unsubscribed => [
+                "unsubscribed",
+                "подписка отменена",
+                "բաժանորդագրությունը չեղարկված է",
+            ],
+            Self::Now => ["now", "сейчас", "հիմա"],
+            Self::NoPairs => [
+                "no pairs, /settings pairs RUB/USD,USD",
+                "нет пар, /settings pairs RUB/USD,USD",
+                "զույգեր չկան, /settings pairs RUB/USD,USD",
+            ],
+            Self::Src => ["src", "источник", "աղբյուր"],
+            Self::State => ["state", "статус", "վիճակ"],
+            Self::Age => ["age", "возраст", "տարիք"],
+            Self::Fails => ["fails", "сбои", "ձախողում"],
+            Self::Rates => ["rates", "курсы", "փոխարժեք"],
+            Self::Rejected => ["rej", "откл", "մերժ"],
+            Self::Error => ["error", "ошибка", "սխալ"],
+            Self::Ms => ["ms", "мс", "մվ"],
+            Self::Off => ["off", "выкл", "անջատ"],
+            Self::Pause => ["pause", "пауза", "դադար"],
+            Self::Fail => ["fail", "сбой", "ձախողում"],
+            Self::Ok => ["ok", "ок", "լավ"],
+            Self::Alert => ["alert {}", "оповещение {}", "ծանուցում {}"],
+            Self::NoCash => ["no cash", "безнал", "անկանխիկ"],
+            Self::Cash => ["cash", "наличные", "կանխիկ"],
+            Self::Card => ["card", "карта", "քարտ"],
+            Self::Online => ["online", "онлайн", "առցանց"],
+            Self::Cb => ["cb", "ЦБ", "ԿԲ"],
+        })
+    }
+
+    /// Translation with `{}` replaced by `value`.
+    pub fn fmt(self, lang: Lang, value: impl std::fmt::Display) -> String {
+        self.tr(lang).replacen("{}", &value.to_string(), 1)
+    }
+}
+
+impl From<RateType> for Text {
+    fn from(value: RateType) -> Self {
+        match value {
+            RateType::NoCash => Self::NoCash,
+            RateType::Cash => Self::Cash,
+            RateType::Card => Self::Card,
+            RateType::Online => Self::Online,
+            RateType::Cb => Self::Cb,
+        }
+    }
+}
+
+/// Description of the `/command` in the language, `None` to keep the
+/// English one from `Command`.
+pub fn command_description(lang: Lang, command: &str) -> Option<&'static str> {
+    let [ru, hy] = match command.trim_start_matches('/') {
+        "usd" => ["USD ($)", "USD ($)"],
+        "eur" => ["EUR (€)", "EUR (€)"],
+        "rub" => ["RUB (₽)", "RUB (₽)"],
+        "gel" => ["GEL (₾)", "GEL (₾)"],
+        "rubusd" => ["RUB/USD (₽ - $)", "RUB/USD (₽ - $)"],
+        "rubeur" => ["RUB/EUR (₽ - €)", "RUB/EUR (₽ - €)"],
+        "usdeur" => ["USD/EUR ($ - €)", "USD/EUR ($ - €)"],
+        "usdcash" => ["USD наличные ($)", "USD կանխիկ ($)"],
+        "eurcash" => ["EUR наличные (€)", "EUR կանխիկ (€)"],
+        "rubcash" => ["RUB наличные (₽)", "RUB կանխիկ (₽)"],
+        "gelcash" => ["GEL наличные (₾)", "GEL կանխիկ (₾)"],
+        "rubusdcash" => ["RUB/USD наличные (₽ - $)", "RUB/USD կանխիկ (₽ - $)"],
+        "rubeurcash" => ["RUB/EUR наличные (₽ - €)", "RUB/EUR կանխիկ (₽ - €)"],
+        "usdeurcash" => ["USD/EUR наличные ($ - €)", "USD/EUR կանխիկ ($ - €)"],
+        "conv" => [
+            "<AMOUNT>? <FROM> <TO>? <text|image>?",
+            "<AMOUNT>? <FROM> <TO>? <text|image>?",
+        ],
+        "convcash" => [
+            "<AMOUNT>? <FROM> <TO>? <text|image>? наличные",
+            "<AMOUNT>? <FROM> <TO>? <text|image>? կանխիկ",
+        ],
+        "route" => [
+            "<AMOUNT>? <FROM> <TO>? через все источники",
+            "<AMOUNT>? <FROM> <TO>? բոլոր աղբյուրներով",
+        ],
+        "alert" => [
+            "<FROM> <TO>? >|< <VALUE> оповещение",
+            "<FROM> <TO>? >|< <VALUE> ծանուցում",
+        ],
+        "alertcash" => [
+            "<FROM> <TO>? >|< <VALUE> оповещение, наличные",
+            "<FROM> <TO>? >|< <VALUE> ծանուցում, կանխիկ",
+        ],
+        "alerts" => ["список оповещений", "ծանուցումների ցանկ"],
+        "unalert" => ["<ID> удалить оповещение", "<ID> ջնջել ծանուցումը"],
+        "digest" => [
+            "подписаться на ежедневную сводку",
+            "բաժանորդագրվել օրական ամփոփագրին",
+        ],
+        "undigest" => [
+            "отписаться от ежедневной сводки",
+            "չեղարկել օրական ամփոփագիրը",
+        ],
+        "settings" => [
+            "<KEY>? <VALUE>? настройки",
+            "<KEY>? <VALUE>? կարգավորումներ",
+        ],
+        "fav" => ["избранные пары", "ընտրված զույգեր"],
+        "history" => [
+            "<SOURCE>|<FROM> <TO>? <DAYS>d? история",
+            "<SOURCE>|<FROM> <TO>? <DAYS>d? պատմություն",
+        ],
+        "chart" => [
+            "<FROM> <TO>? <DAYS>d? <SOURCE>* график",
+            "<FROM> <TO>? <DAYS>d? <SOURCE>* գրաֆիկ",
+        ],
+        "get" => ["<SOURCE>", "<SOURCE>"],
+        "getcash" => ["<SOURCE> наличные", "<SOURCE> կանխիկ"],
+        "getcard" => ["<SOURCE> карта", "<SOURCE> քարտ"],
+        "list" => ["список источников", "աղբյուրների ցանկ"],
+        "info" => ["о боте", "բոտի մասին"],
+        "status" => ["статус источников", "աղբյուրների վիճակ"],
+        _ => return None,
+    };
+    match lang {
+        Lang::En => None,
+        Lang::Ru => Some(ru),
+        Lang::Hy => Some(hy),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(Lang::from_code("ru"), Some(Lang::Ru));
+        assert_eq!(Lang::from_code("hy-AM"), Some(Lang::Hy));
+        assert_eq!(Lang::from_code("en-US"), Some(Lang::En));
+        assert_eq!(Lang::from_code("de"), None);
+    }
+
+    #[test]
+    fn test_tr() {
+        assert_eq!(Text::Now.tr(Lang::Ru), "сейчас");
+        assert_eq!(Text::Updated.fmt(Lang::En, "5m"), "updated 5m ago");
+        assert_eq!(
+            command_description(Lang::Hy, "/list"),
+            Some("աղբյուրների ցանկ")
+        );
+        assert_eq!(command_description(Lang::En, "/list"), None);
+        assert_eq!(
+            command_description(Lang::Ru, "/usdcash"),
+            Some("USD наличные ($)")
+        );
+        assert_eq!(command_description(Lang::Ru, "/unknown"), None);
+    }
+}
@@ -1,9 +1,17 @@
+pub mod alert;
 pub mod bot;
+pub mod chart;
 pub mod collector;
 pub mod config;
 pub mod database;
 pub mod generate;
 pub mod graph;
+pub mod i18n;
+#[cfg(test)]
+mod mock;
+pub mod settings;
 pub mod source;
+pub mod store;
+pub mod table;
 
 pub const DUNNO: &str = r"¯\_(ツ)_/¯";
@@ -0,0 +1,337 @@
+//! In process HTTP server emulating the bank endpoints with the fixtures, for
+//! end to end collector tests.
+
+use crate::config::Config;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+const JSON: &str = "application/json";
+const XML: &str = "text/xml";
+const HTML: &str = "text/html";
+
+struct Endpoint {
+    host: &'static str,
+    path: &'static str,
+    method: Method,
+    content_type: &'static str,
+    body: &'static str,
+}
+
+static ENDPOINTS: &[Endpoint] = &[
+    Endpoint {
+        host: "www.acbadigital.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/acba.json"),
+    },
+    Endpoint {
+        host: "mobile.aeb.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/aeb.json"),
+    },
+    Endpoint {
+        host: "online.ameriabank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/armsoft.json"),
+    },
+    Endpoint {
+        host: "internetbank.amiobank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/armsoft.json"),
+    },
+    Endpoint {
+        host: "direct.araratbank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/armsoft.json"),
+    },
+    Endpoint {
+        host: "online.byblosbankarmenia.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/armsoft.json"),
+    },
+    Endpoint {
+        host: "online.evocabank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/armsoft.json"),
+    },
+    Endpoint {
+        host: "website-api.ardshinbank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/ardshin.json"),
+    },
+    Endpoint {
+        host: "www.armswissbank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/armswiss.json"),
+    },
+    Endpoint {
+        host: "m.artsakhbank.am",
+        path: "",
+        method: Method::POST,
+        content_type: XML,
+        body: include_str!("../fixtures/lsoft.xml"),
+    },
+    Endpoint {
+        host: "avosend.com",
+        path: "",
+        method: Method::POST,
+        content_type: HTML,
+        body: include_str!("../fixtures/avosend.txt"),
+    },
+    Endpoint {
+        host: "api.cba.am",
+        path: "",
+        method: Method::POST,
+        content_type: XML,
+        body: include_str!("../fixtures/cb.xml"),
+    },
+    Endpoint {
+        host: "sapi.conversebank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/converse.json"),
+    },
+    Endpoint {
+        host: "mobileapi.fcc.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/fast.json"),
+    },
+    Endpoint {
+        host: "www.idbanking.am",
+        path: "",
+        method: Method::POST,
+        content_type: JSON,
+        body: include_str!("../fixtures/idbank.json"),
+    },
+    Endpoint {
+        host: "www.inecobank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/ineco.json"),
+    },
+    Endpoint {
+        host: "api.mellatbank.am",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/mellat.json"),
+    },
+    Endpoint {
+        host: "api-user.vamprivet.ru",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/mir.json"),
+    },
+    Endpoint {
+        host: "invest-public-api.tinkoff.ru",
+        path: "GetOrderBook",
+        method: Method::POST,
+        content_type: JSON,
+        body: include_str!("../fixtures/moex_order_book.json"),
+    },
+    Endpoint {
+        host: "invest-public-api.tinkoff.ru",
+        path: "CurrencyBy",
+        method: Method::POST,
+        content_type: JSON,
+        body: include_str!("../fixtures/moex_currency.json"),
+    },
+    Endpoint {
+        host: "www.sas.am",
+        path: "",
+        method: Method::GET,
+        content_type: HTML,
+        body: include_str!("../fixtures/sas.html"),
+    },
+    Endpoint {
+        host: "force.unibank.am",
+        path: "",
+        method: Method::POST,
+        content_type: XML,
+        body: include_str!("../fixtures/lsoft.xml"),
+    },
+    Endpoint {
+        host: "www.unionpayintl.com",
+        path: "",
+        method: Method::GET,
+        content_type: JSON,
+        body: include_str!("../fixtures/unionpay.json"),
+    },
+    Endpoint {
+        host: "vtb.am",
+        path: "",
+        method: Method::GET,
+        content_type: HTML,
+        body: include_str!("../fixtures/vtb.html"),
+    },
+];
+
+/// Misbehaviour of a bank endpoint.
+#[derive(Debug, Clone, Copy)]
+pub enum Fault {
+    /// Respond with the status code and an empty body.
+    Status(StatusCode),
+    /// Never respond, the client times out.
+    Hang,
+    /// Respond with the first half of the fixture.
+    Malformed,
+    /// Respond with 503 to the next `n` requests.
+    Flaky(u32),
+}
+
+#[derive(Default)]
+struct Shared {
+    faults: Mutex<HashMap<String, Fault>>,
+    hits: Mutex<HashMap<String, u32>>,
+}
+
+pub struct Server {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl Server {
+    pub async fn start() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+        let app = Router::new().fallback(handle).with_state(shared.clone());
+        let task = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("panic");
+        });
+        Ok(Self { addr, shared, task })
+    }
+
+    /// `config/config.toml` with every bank host pointing at the server.
+    pub fn config(&self) -> Config {
+        let s = rewrite_urls(
+            include_str!("../config/config.toml"),
+            &format!("http://{}", self.addr),
+        );
+        toml::from_str(&s).expect("panic")
+    }
+
+    pub fn set_fault(&self, host: &str, fault: Fault) {
+        self.shared
+            .faults
+            .lock()
+            .unwrap()
+            .insert(host.into(), fault);
+    }
+
+    /// Number of requests the host received.
+    pub fn hits(&self, host: &str) -> u32 {
+        self.shared
+            .hits
+            .lock()
+            .unwrap()
+            .get(host)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn rewrite_urls(s: &str, base: &str) -> String {
+    const SCHEME: &str = "https://";
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(idx) = rest.find(SCHEME) {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + SCHEME.len()..];
+        let end = rest.find(['/', '"']).unwrap_or(rest.len());
+        let host = rest[..end].split(':').next().unwrap_or_default();
+        result.push_str(&format!("{base}/{host}"));
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+async fn handle(
+    State(shared): State<Arc<Shared>>,
+    method: Method,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    let (host, path) = path.split_once('/').unwrap_or((path, ""));
+    let Some(endpoint) = ENDPOINTS
+        .iter()
+        .find(|v| v.host == host && path.contains(v.path))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    *shared.hits.lock().unwrap().entry(host.into()).or_default() += 1;
+    if method != endpoint.method {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    if host == "invest-public-api.tinkoff.ru" && !headers.contains_key(header::AUTHORIZATION) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let fault = {
+        let mut faults = shared.faults.lock().unwrap();
+        let fault = faults.get(host).copied();
+        if let Some(Fault::Flaky(n)) = fault {
+            if n > 1 {
+                faults.insert(host.into(), Fault::Flaky(n - 1));
+            } else {
+                faults.remove(host);
+            }
+        }
+        fault
+    };
+    let mut body = endpoint.body;
+    match fault {
+        Some(Fault::Status(status)) => return status.into_response(),
+        Some(Fault::Flaky(_)) => return StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Some(Fault::Hang) => std::future::pending().await,
+        Some(Fault::Malformed) => {
+            let mut end = body.len() / 2;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body = &body[..end];
+        }
+        None => {}
+    }
+    ([(header::CONTENT_TYPE, endpoint.content_type)], body).into_response()
+}
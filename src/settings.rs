@@ -0,0 +1,206 @@
+//! Per user preferences applied on top of `config::Gen`.
+
+use crate::{
+    config::{self, TableFormat},
+    i18n::Lang,
+    source::{Currency, RateType},
+};
+use anyhow::{anyhow, ensure};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+const CATEGORIES: &str = "*#@";
+const MAX_PAIRS: usize = 10;
+const MAX_DP: u32 = 8;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Rate type of commands without an explicit one.
+    pub rate_type: Option<RateType>,
+    /// Pairs shown by `/fav`.
+    pub pairs: Vec<(Currency, Currency)>,
+    /// Source categories shown, `*` banks, `#` transfers and `@` cb.
+    pub show: Option<String>,
+    pub rate_dp: Option<u32>,
+    pub diff_dp: Option<u32>,
+    /// Language of the replies, by default from the Telegram client.
+    pub lang: Option<Lang>,
+    /// `/conv` tables as text or image.
+    pub table: Option<TableFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::EnumString, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum Key {
+    RateType,
+    Pairs,
+    Show,
+    RateDp,
+    DiffDp,
+    Lang,
+    Table,
+}
+
+impl Settings {
+    /// Sets the key from the user input, an empty value resets it.
+    pub fn set(&mut self, key: Key, value: &str) -> anyhow::Result<()> {
+        let value = value.trim();
+        if value.is_empty() {
+            match key {
+                Key::RateType => self.rate_type = None,
+                Key::Pairs => self.pairs.clear(),
+                Key::Show => self.show = None,
+                Key::RateDp => self.rate_dp = None,
+                Key::DiffDp => self.diff_dp = None,
+                Key::Lang => self.lang = None,
+                Key::Table => self.table = None,
+            }
+            return Ok(());
+        }
+        match key {
+            Key::RateType => {
+                let v = RateType::from_str(value).map_err(|_| anyhow!("invalid rate type"))?;
+                ensure!(v != RateType::Cb, "invalid rate type");
+                self.rate_type = Some(v);
+            }
+            Key::Pairs => {
+                let pairs = value
+                    .split([',', ' '])
+                    .filter(|v| !v.is_empty())
+                    .map(|v| {
+                        let (from, to) = v.split_once('/').unwrap_or(("", v));
+                        let from = if from.is_empty() {
+                            Currency::default()
+                        } else {
+                            Currency::new(from)
+                        };
+                        let to = Currency::new(to);
+                        ensure!(!to.is_empty() && from != to, "invalid pair: {v}");
+                        Ok((from, to))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                ensure!(pairs.len() <= MAX_PAIRS, "max pairs: {MAX_PAIRS}");
+                self.pairs = pairs;
+            }
+            Key::Show => {
+                ensure!(
+                    value.chars().all(|c| CATEGORIES.contains(c)),
+                    "expected some of {CATEGORIES}"
+                );
+                self.show = Some(CATEGORIES.chars().filter(|c| value.contains(*c)).collect());
+            }
+            Key::RateDp | Key::DiffDp => {
+                let v = u32::from_str(value)?;
+                ensure!(v <= MAX_DP, "max {key}: {MAX_DP}");
+                if key == Key::RateDp {
+                    self.rate_dp = Some(v);
+                } else {
+                    self.diff_dp = Some(v);
+                }
+            }
+            Key::Lang => {
+                let v = Lang::from_str(value).map_err(|_| {
+                    let langs = Lang::iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                    anyhow!("expected one of {}", langs.join(", "))
+                })?;
+                self.lang = Some(v);
+            }
+            Key::Table => {
+                let v = TableFormat::from_str(value)
+                    .map_err(|_| anyhow!("expected one of text, image"))?;
+                self.table = Some(v);
+            }
+        }
+        Ok(())
+    }
+
+    /// Value of the key as entered, empty if not set.
+    pub fn get(&self, key: Key) -> String {
+        match key {
+            Key::RateType => self
+                .rate_type
+                .map(|v| format!("{v:?}").to_lowercase())
+                .unwrap_or_default(),
+            Key::Pairs => self
+                .pairs
+                .iter()
+                .map(|(from, to)| format!("{from}/{to}"))
+                .collect::<Vec<_>>()
+                .join(","),
+            Key::Show => self.show.clone().unwrap_or_default(),
+            Key::RateDp => self.rate_dp.map(|v| v.to_string()).unwrap_or_default(),
+            Key::DiffDp => self.diff_dp.map(|v| v.to_string()).unwrap_or_default(),
+            Key::Lang => self.lang.map(|v| v.to_string()).unwrap_or_default(),
+            Key::Table => self.table.map(|v| v.to_string()).unwrap_or_default(),
+        }
+    }
+
+    /// Every key with its value.
+    pub fn to_lines(&self) -> Vec<String> {
+        Key::iter()
+            .map(|key| format!("{key}: {}", self.get(key)))
+            .collect()
+    }
+
+    pub fn apply(&self, gen: &config::Gen) -> config::Gen {
+        let mut gen = gen.clone();
+        if let Some(v) = self.rate_dp {
+            gen.rate_dp = v;
+        }
+        if let Some(v) = self.diff_dp {
+            gen.diff_dp = v;
+        }
+        if let Some(v) = &self.show {
+            gen.show = v.clone();
+        }
+        if let Some(v) = self.lang {
+            gen.lang = v;
+        }
+        if let Some(v) = self.table {
+            gen.table = v;
+        }
+        gen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() -> anyhow::Result<()> {
+        let mut settings = Settings::default();
+        settings.set(Key::RateType, "cash")?;
+        assert_eq!(settings.rate_type, Some(RateType::Cash));
+        assert!(settings.set(Key::RateType, "cb").is_err());
+        settings.set(Key::Pairs, "rub/usd, usd")?;
+        assert_eq!(
+            settings.pairs,
+            vec![
+                (Currency::rub(), Currency::usd()),
+                (Currency::default(), Currency::usd())
+            ]
+        );
+        assert_eq!(settings.get(Key::Pairs), "RUB/USD,AMD/USD");
+        assert!(settings.set(Key::Pairs, "usd/usd").is_err());
+        settings.set(Key::Show, "@*")?;
+        assert_eq!(settings.show.as_deref(), Some("*@"));
+        assert!(settings.set(Key::Show, "*x").is_err());
+        settings.set(Key::RateDp, "2")?;
+        assert!(settings.set(Key::DiffDp, "9").is_err());
+        settings.set(Key::Lang, "RU")?;
+        assert_eq!(settings.lang, Some(Lang::Ru));
+        assert!(settings.set(Key::Lang, "de").is_err());
+        settings.set(Key::Table, "img")?;
+        assert_eq!(settings.table, Some(TableFormat::Image));
+        assert_eq!(settings.get(Key::Table), "image");
+        settings.set(Key::RateType, "")?;
+        assert_eq!(settings.rate_type, None);
+        let s = serde_json::to_string(&settings)?;
+        assert_eq!(serde_json::from_str::<Settings>(&s)?, settings);
+        assert_eq!(serde_json::from_str::<Settings>("{}")?, Settings::default());
+        Ok(())
+    }
+}
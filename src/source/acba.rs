@@ -78,3 +78,32 @@ pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<ModRate>> {
     }
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/acba.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 25);
+        assert_eq!(rates[0].from, Currency::usd());
+        assert_eq!(rates[0].to, Currency::default());
+        assert_eq!(rates[0].rate_type, RateType::NoCash);
+        assert_eq!(rates[0].buy, Some(dec!(384)));
+        assert_eq!(rates[0].sell, Some(dec!(390)));
+        assert_eq!(rates[2].from, Currency::rub());
+        assert_eq!(rates[2].buy, Some(dec!(4.21)));
+        assert_eq!(rates[6].rate_type, RateType::Cash);
+        assert_eq!(rates[6].sell, Some(dec!(390.5)));
+        assert_eq!(rates[12].rate_type, RateType::Card);
+        assert_eq!(rates[12].buy, Some(dec!(384)));
+        assert_eq!(rates[19].from, Currency::usd());
+        assert_eq!(rates[19].to, Currency::rub());
+        assert_eq!(rates[19].rate_type, RateType::NoCash);
+        assert_eq!(rates[19].sell, Some(dec!(92.7553444)));
+        Ok(())
+    }
+}
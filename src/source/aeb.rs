@@ -32,6 +32,10 @@ pub struct Rate {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<ModRate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<ModRate>> {
     let mut rates = vec![];
     for item in resp.rate_currency_settings {
         for rate in item
@@ -50,3 +54,24 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/aeb.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 6);
+        assert_eq!(rates[0].from, Currency::usd());
+        assert_eq!(rates[0].rate_type, RateType::NoCash);
+        assert_eq!(rates[0].buy, Some(dec!(386.5)));
+        assert_eq!(rates[0].sell, Some(dec!(391.5)));
+        assert_eq!(rates[5].from, Currency::rub());
+        assert_eq!(rates[5].rate_type, RateType::Cash);
+        assert_eq!(rates[5].buy, None);
+        Ok(())
+    }
+}
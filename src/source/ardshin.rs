@@ -29,6 +29,10 @@ pub struct Currency {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let mut results = vec![];
     for (rate_type, rates) in [
         (RateType::NoCash, resp.data.currencies.no_cash),
@@ -36,8 +40,7 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     ] {
         let rates = rates
             .iter()
-            .cloned()
-            .filter_map(|v| v)
+            .flatten()
             .map(|v| Rate {
                 from: v.curr_type.clone(),
                 to: ModCurrency::default(),
@@ -50,3 +53,22 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/ardshin.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 6);
+        assert_eq!(rates[0].rate_type, RateType::NoCash);
+        assert_eq!(rates[0].buy, Some(dec!(386.5)));
+        assert_eq!(rates[3].from, ModCurrency::usd());
+        assert_eq!(rates[3].rate_type, RateType::Cash);
+        assert_eq!(rates[3].sell, Some(dec!(392)));
+        Ok(())
+    }
+}
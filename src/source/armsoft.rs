@@ -22,7 +22,7 @@ pub struct ExchangeRate {
     pub rate_for: Decimal,
 }
 
-fn _collect(resp: Response, rate_type: RateType) -> Vec<Rate> {
+pub(crate) fn parse(resp: Response, rate_type: RateType) -> Vec<Rate> {
     resp.array_of_exchange_rate
         .iter()
         .map(|v| Rate {
@@ -39,8 +39,26 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     let mut results = vec![];
     for rate_type in [RateType::NoCash, RateType::Cash] {
         let resp: Response = get_json_for_rate_type(client, config, rate_type).await?;
-        let rates = _collect(resp, rate_type);
+        let rates = parse(resp, rate_type);
         results.extend_from_slice(&rates);
     }
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/armsoft.json"))?;
+        let rates = parse(resp, RateType::Cash);
+        assert_eq!(rates.len(), 4);
+        assert!(rates.iter().all(|v| v.rate_type == RateType::Cash));
+        assert_eq!(rates[2].from, Currency::rub());
+        assert_eq!(rates[3].buy, Some(dec!(2.65)));
+        assert_eq!(rates[3].sell, Some(dec!(2.75)));
+        Ok(())
+    }
+}
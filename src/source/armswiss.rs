@@ -24,6 +24,10 @@ pub struct LmasbRate {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let mut rates = vec![];
     let to = Currency::default();
     for rate in resp.lmasbrate {
@@ -56,3 +60,21 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/armswiss.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 8);
+        assert_eq!(rates[1].rate_type, RateType::Cash);
+        assert_eq!(rates[1].sell, Some(dec!(392)));
+        assert_eq!(rates[6].from, Currency::new("JPY"));
+        assert_eq!(rates[6].buy, Some(dec!(2.65)));
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::source::{Currency, Rate, RateType};
+use crate::source::{http, Currency, Options, Rate, RateType};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
@@ -25,10 +25,12 @@ pub struct Request {
 pub struct Config {
     pub rates_url: String,
     pub enabled: bool,
+    #[serde(flatten)]
+    pub options: Options,
     pub req: Request,
 }
 
-async fn post(client: &reqwest::Client, config: &Config) -> anyhow::Result<Response> {
+async fn post(client: &reqwest::Client, config: &Config) -> anyhow::Result<String> {
     let req_data = Request {
         country_code_from: config.req.country_code_from.clone(),
         country_id_from: config.req.country_id_from,
@@ -39,28 +41,26 @@ async fn post(client: &reqwest::Client, config: &Config) -> anyhow::Result<Respo
         summ_send: config.req.summ_send,
         direction: config.req.direction.clone(),
     };
-    let mut resp = client
-        .post(&config.rates_url)
-        .form(&req_data)
-        .header(
-            reqwest::header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
-        )
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
-    const CLOSE_SCRIPT_TAG: &str = "</script>";
-    if let Some(idx) = resp.find(CLOSE_SCRIPT_TAG) {
-        resp.drain(..idx + CLOSE_SCRIPT_TAG.len());
-    }
-    let resp = serde_json::from_str(&resp.trim())?;
-    Ok(resp)
+    let body = http::send(client.post(&config.rates_url).form(&req_data).header(
+        reqwest::header::CONTENT_TYPE,
+        "application/x-www-form-urlencoded",
+    ))
+    .await?;
+    Ok(body)
 }
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
-    let resp: Response = post(client, config).await?;
+    let body = post(client, config).await?;
+    parse(&body)
+}
+
+pub(crate) fn parse(body: &str) -> anyhow::Result<Vec<Rate>> {
+    const CLOSE_SCRIPT_TAG: &str = "</script>";
+    let body = match body.find(CLOSE_SCRIPT_TAG) {
+        Some(idx) => &body[idx + CLOSE_SCRIPT_TAG.len()..],
+        None => body,
+    };
+    let resp: Response = serde_json::from_str(body.trim())?;
     Ok(vec![Rate {
         from: Currency::rub(),
         to: Currency::default(),
@@ -69,3 +69,19 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
         sell: None,
     }])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let rates = parse(include_str!("../../fixtures/avosend.txt"))?;
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].from, Currency::rub());
+        assert_eq!(rates[0].buy, Some(dec!(4.85)));
+        assert!(parse(r#"{"convertRate":4.9}"#).is_ok());
+        Ok(())
+    }
+}
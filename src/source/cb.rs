@@ -1,5 +1,5 @@
 pub use crate::source::BaseConfig as Config;
-use crate::source::{de, BaseConfigTrait, Currency, Rate, RateType};
+use crate::source::{de, http, BaseConfigTrait, Currency, Rate, RateType};
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
@@ -88,24 +88,26 @@ where
             },
         },
     };
-    let xml = client
-        .post(config.rates_url())
-        .header(
-            reqwest::header::CONTENT_TYPE,
-            "application/soap+xml; charset=utf-8",
-        )
-        .body(quick_xml::se::to_string(&req_data)?)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let xml = http::send(
+        client
+            .post(config.rates_url())
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/soap+xml; charset=utf-8",
+            )
+            .body(quick_xml::se::to_string(&req_data)?),
+    )
+    .await?;
     let resp = quick_xml::de::from_str(&xml)?;
     Ok(resp)
 }
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp = post(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let rates = resp
         .soap_body
         .exchange_rates_latest_response
@@ -126,3 +128,21 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
         .collect();
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = quick_xml::de::from_str(include_str!("../../fixtures/cb.xml"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 4);
+        assert!(rates.iter().all(|v| v.rate_type == RateType::Cb));
+        assert_eq!(rates[0].from, Currency::usd());
+        assert_eq!(rates[0].buy, Some(dec!(387.19)));
+        assert_eq!(rates[3].sell, Some(dec!(2.743)));
+        Ok(())
+    }
+}
@@ -33,6 +33,10 @@ pub struct Currency {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let mut results = vec![];
     for (rate_type, rates) in [
         (RateType::NoCash, resp.non_cash),
@@ -54,3 +58,22 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/converse.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 6);
+        assert_eq!(rates[2].from, ModCurrency::eur());
+        assert_eq!(rates[2].to, ModCurrency::usd());
+        assert_eq!(rates[2].buy, Some(dec!(1.08)));
+        assert_eq!(rates[4].from, ModCurrency::rub());
+        assert_eq!(rates[5].rate_type, RateType::Card);
+        Ok(())
+    }
+}
@@ -26,18 +26,38 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     let mut results = vec![];
     for rate_type in [RateType::NoCash, RateType::Cash] {
         let resp: Response = get_json_for_rate_type(client, config, rate_type).await?;
-        let rates = resp
-            .rates
-            .iter()
-            .map(|v| ModRate {
-                from: v.id.clone(),
-                to: Currency::default(),
-                rate_type,
-                buy: Some(v.buy / v.unit),
-                sell: Some(v.sale / v.unit),
-            })
-            .collect::<Vec<_>>();
+        let rates = parse(resp, rate_type);
         results.extend_from_slice(&rates);
     }
     Ok(results)
 }
+
+pub(crate) fn parse(resp: Response, rate_type: RateType) -> Vec<ModRate> {
+    resp.rates
+        .iter()
+        .map(|v| ModRate {
+            from: v.id.clone(),
+            to: Currency::default(),
+            rate_type,
+            buy: Some(v.buy / v.unit),
+            sell: Some(v.sale / v.unit),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/fast.json"))?;
+        let rates = parse(resp, RateType::NoCash);
+        assert_eq!(rates.len(), 3);
+        assert_eq!(rates[2].from, Currency::rub());
+        assert_eq!(rates[2].buy, Some(dec!(4.2)));
+        assert_eq!(rates[2].sell, Some(dec!(4.45)));
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Context;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::LazyLock,
+};
+use tokio::fs;
+
+const ENV_HTTP_MODE: &str = "BOT_HTTP_MODE";
+const ENV_HTTP_DIR: &str = "BOT_HTTP_DIR";
+const DEFAULT_HTTP_DIR: &str = "./data/http";
+const MAX_NAME_LEN: usize = 128;
+
+/// How source requests reach the network, selected by `BOT_HTTP_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Mode {
+    /// Plain requests, nothing is saved.
+    #[default]
+    Off,
+    /// Requests go to the network and raw bodies are saved to `BOT_HTTP_DIR`.
+    Record,
+    /// Bodies are served from `BOT_HTTP_DIR`, the network is never used.
+    Replay,
+}
+
+static MODE: LazyLock<Mode> = LazyLock::new(|| {
+    let Ok(s) = env::var(ENV_HTTP_MODE) else {
+        return Mode::default();
+    };
+    Mode::from_str(&s).unwrap_or_else(|_| {
+        log::error!("invalid {ENV_HTTP_MODE}: {s}");
+        Mode::default()
+    })
+});
+
+static DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    env::var(ENV_HTTP_DIR)
+        .unwrap_or_else(|_| DEFAULT_HTTP_DIR.into())
+        .into()
+});
+
+pub fn mode() -> Mode {
+    *MODE
+}
+
+/// Sends the request and returns the response body as text, recording or
+/// replaying it according to `BOT_HTTP_MODE`.
+pub(crate) async fn send(req: reqwest::RequestBuilder) -> anyhow::Result<String> {
+    send_with(*MODE, &DIR, req).await
+}
+
+async fn send_with(mode: Mode, dir: &Path, req: reqwest::RequestBuilder) -> anyhow::Result<String> {
+    let (client, req) = req.build_split();
+    let req = req?;
+    let path = dir.join(key(&req));
+    if mode == Mode::Replay {
+        return fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("replay {} {}", req.method(), req.url()));
+    }
+    let body = client
+        .execute(req)
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    if mode == Mode::Record {
+        fs::create_dir_all(dir).await?;
+        fs::write(&path, &body).await?;
+    }
+    Ok(body)
+}
+
+/// File name for the request, readable prefix of the url plus a hash of the
+/// method, url and body so that different requests never share a file.
+fn key(req: &reqwest::Request) -> String {
+    let url = req.url().as_str();
+    let body = req.body().and_then(|v| v.as_bytes()).unwrap_or_default();
+    let hash = fnv1a(&[req.method().as_str().as_bytes(), url.as_bytes(), body]);
+    let name: String = url
+        .split_once("://")
+        .map_or(url, |(_, v)| v)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_NAME_LEN)
+        .collect();
+    format!("{}_{name}_{hash:016x}", req.method())
+}
+
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in parts.iter().flat_map(|v| v.iter()) {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() -> anyhow::Result<()> {
+        let client = reqwest::Client::new();
+        let get = client.get("https://example.com/rates/1?lang=en").build()?;
+        let key1 = key(&get);
+        assert!(key1.starts_with("GET_example.com_rates_1_lang_en_"));
+        assert_eq!(key1, key(&client.get(get.url().clone()).build()?));
+        let post1 = client.post(get.url().clone()).body("a").build()?;
+        let post2 = client.post(get.url().clone()).body("b").build()?;
+        assert_ne!(key(&post1), key(&post2));
+        let long = format!("https://example.com/{}", "a".repeat(500));
+        assert!(key(&client.get(long).build()?).len() < 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay() -> anyhow::Result<()> {
+        let dir = env::temp_dir().join(format!("am-rate-bot-http-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let client = reqwest::Client::new();
+        let url = "http://127.0.0.1:1/rates";
+        std::fs::write(dir.join(key(&client.get(url).build()?)), "{}")?;
+        let body = send_with(Mode::Replay, &dir, client.get(url)).await?;
+        assert_eq!(body, "{}");
+        assert!(send_with(Mode::Replay, &dir, client.post(url))
+            .await
+            .is_err());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
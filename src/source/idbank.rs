@@ -1,5 +1,5 @@
 pub use crate::source::BaseConfig as Config;
-use crate::source::{de, BaseConfigTrait, Currency, Rate, RateType, USER_AGENT};
+use crate::source::{de, http, BaseConfigTrait, Currency, Rate, RateType, USER_AGENT};
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
@@ -42,24 +42,27 @@ pub(crate) async fn post<T>(client: &reqwest::Client, config: &T) -> anyhow::Res
 where
     T: BaseConfigTrait,
 {
-    let resp = client
-        .post(config.rates_url())
-        .header(reqwest::header::CONTENT_LENGTH, 0)
-        .header(reqwest::header::USER_AGENT, USER_AGENT)
-        .send()
-        .await?
-        .error_for_status()?
-        .json()
-        .await?;
+    let body = http::send(
+        client
+            .post(config.rates_url())
+            .header(reqwest::header::CONTENT_LENGTH, 0)
+            .header(reqwest::header::USER_AGENT, USER_AGENT),
+    )
+    .await?;
+    let resp = serde_json::from_str(&body)?;
     Ok(resp)
 }
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp: Response = post(client, config).await?;
+    Ok(parse(&resp))
+}
+
+pub(crate) fn parse(resp: &Response) -> Vec<Rate> {
     let mut rates = vec![];
     let to = Currency::default();
-    for rate in resp.result.currency_rate {
-        let from = rate.iso_txt;
+    for rate in &resp.result.currency_rate {
+        let from = &rate.iso_txt;
         rates.push(Rate {
             from: from.clone(),
             to: to.clone(),
@@ -82,5 +85,24 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
             sell: rate.cards_sell,
         });
     }
-    Ok(rates)
+    rates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/idbank.json"))?;
+        let rates = parse(&resp);
+        assert_eq!(rates.len(), 9);
+        assert_eq!(rates[3].from, Currency::rub());
+        assert_eq!(rates[3].buy, Some(dec!(4.2)));
+        assert_eq!(rates[5].rate_type, RateType::Card);
+        assert_eq!(rates[6].buy, None);
+        assert_eq!(rates[7].sell, Some(dec!(155)));
+        Ok(())
+    }
 }
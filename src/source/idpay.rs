@@ -1,34 +1,22 @@
 pub use crate::source::idbank::Response;
-use crate::source::{idbank, percent, BaseConfigTrait, Currency, Error, Rate, RateType};
+use crate::source::{percent, Currency, Error, Options, Rate, RateType};
 use anyhow::bail;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
-    pub rates_url: String,
     pub enabled: bool,
+    #[serde(flatten)]
+    pub options: Options,
     pub commission_rate: Decimal,
     pub commission_rate_to_ru_card: Decimal,
 }
 
-impl BaseConfigTrait for Config {
-    fn rates_url(&self) -> &str {
-        &self.rates_url
-    }
-}
-
-pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
-    let resp: Response = idbank::post(client, config).await?;
+pub(crate) fn parse(resp: &Response, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let to = Currency::default();
     let from = Currency::rub();
-    let Some(rate) = resp
-        .result
-        .currency_rate
-        .iter()
-        .filter(|v| v.iso_txt == from)
-        .next()
-    else {
+    let Some(rate) = resp.result.currency_rate.iter().find(|v| v.iso_txt == from) else {
         bail!(Error::NoRates);
     };
     let mut rate_buy = None;
@@ -71,3 +59,28 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
         },
     ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::idbank;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/idbank.json"))?;
+        let config = Config {
+            enabled: true,
+            options: Options::default(),
+            commission_rate: dec!(1),
+            commission_rate_to_ru_card: dec!(2),
+        };
+        let rates = parse(&resp, &config)?;
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].buy, Some(dec!(4.158)));
+        assert_eq!(rates[0].sell, Some(dec!(4.5835)));
+        assert_eq!(rates[1].buy, Some(dec!(4.851)));
+        assert_eq!(idbank::parse(&resp).len(), 9);
+        Ok(())
+    }
+}
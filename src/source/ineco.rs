@@ -28,6 +28,10 @@ pub struct Rate {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<ModRate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<ModRate>> {
     let mut rates = vec![];
     let to = Currency::default();
     for item in resp.items {
@@ -63,3 +67,21 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/ineco.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 8);
+        assert_eq!(rates[3].rate_type, RateType::Online);
+        assert_eq!(rates[3].buy, Some(dec!(387)));
+        assert_eq!(rates[6].rate_type, RateType::Card);
+        assert_eq!(rates[6].buy, None);
+        Ok(())
+    }
+}
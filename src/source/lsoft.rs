@@ -1,5 +1,5 @@
 pub use crate::source::BaseConfig as Config;
-use crate::source::{de, BaseConfigTrait, Currency, Rate, RateType, USER_AGENT};
+use crate::source::{de, http, BaseConfigTrait, Currency, Rate, RateType, USER_AGENT};
 use rust_decimal::Decimal;
 use serde::{de::DeserializeOwned, Deserialize};
 
@@ -82,15 +82,13 @@ where
         get_currency_list_parameters: Default::default(),
         userid: "".into(),
     };
-    let body = client
-        .post(config.rates_url())
-        .body(quick_xml::se::to_string(&req_data)?)
-        .header(reqwest::header::USER_AGENT, USER_AGENT)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
+    let body = http::send(
+        client
+            .post(config.rates_url())
+            .body(quick_xml::se::to_string(&req_data)?)
+            .header(reqwest::header::USER_AGENT, USER_AGENT),
+    )
+    .await?;
     let resp: T1 = quick_xml::de::from_str(&body)?;
     Ok(resp)
 }
@@ -100,6 +98,10 @@ where
     T1: BaseConfigTrait,
 {
     let resp: Response = post(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let mut rates = vec![];
     let to = Currency::default();
     for item in resp.get_currency_list.currency_list {
@@ -121,3 +123,22 @@ where
     }
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = quick_xml::de::from_str(include_str!("../../fixtures/lsoft.xml"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 8);
+        assert_eq!(rates[0].buy, Some(dec!(386.5)));
+        assert_eq!(rates[5].from, Currency::rub());
+        assert_eq!(rates[5].sell, Some(dec!(4.47)));
+        assert_eq!(rates[6].buy, None);
+        assert_eq!(rates[7].buy, Some(dec!(140)));
+        Ok(())
+    }
+}
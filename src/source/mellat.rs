@@ -35,6 +35,10 @@ pub struct Result {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let mut rates = vec![];
     let to = Currency::default();
     for rate in resp.result.data {
@@ -56,3 +60,21 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/mellat.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 6);
+        assert_eq!(rates[1].rate_type, RateType::Cash);
+        assert_eq!(rates[1].buy, Some(dec!(386)));
+        assert_eq!(rates[4].from, Currency::rub());
+        assert_eq!(rates[4].sell, Some(dec!(4.45)));
+        Ok(())
+    }
+}
@@ -28,13 +28,12 @@ pub struct Currency {
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let resp: Response = get_json(client, config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let to = ModCurrency::default();
-    let Some(rate) = resp
-        .content
-        .iter()
-        .filter(|v| v.currency.strcode == to)
-        .next()
-    else {
+    let Some(rate) = resp.content.iter().find(|v| v.currency.strcode == to) else {
         bail!(Error::NoRates);
     };
     let buy = if rate.value_sell > Decimal::ZERO {
@@ -56,3 +55,22 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     };
     Ok(vec![new_rate(RateType::NoCash), new_rate(RateType::Cash)])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/mir.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].from, ModCurrency::rub());
+        assert_eq!(rates[0].buy, Some(Decimal::ONE / dec!(0.21)));
+        assert_eq!(rates[0].sell, Some(dec!(5)));
+        let resp = Response { content: vec![] };
+        assert!(parse(resp).is_err());
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::ensure;
+use anyhow::{bail, ensure};
 use rust_decimal::Decimal;
 use serde::{de::DeserializeOwned, Deserialize};
 use std::fmt::Debug;
@@ -18,6 +18,7 @@ pub mod cb;
 pub mod converse;
 pub mod evoca;
 pub mod fast;
+pub mod http;
 pub mod idbank;
 pub mod idpay;
 pub mod ineco;
@@ -49,14 +50,13 @@ where
     T1: DeserializeOwned,
     T2: BaseConfigTrait,
 {
-    let resp = client
-        .get(config.rates_url())
-        .header(reqwest::header::USER_AGENT, USER_AGENT)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<T1>()
-        .await?;
+    let body = http::send(
+        client
+            .get(config.rates_url())
+            .header(reqwest::header::USER_AGENT, USER_AGENT),
+    )
+    .await?;
+    let resp = serde_json::from_str(&body)?;
     Ok(resp)
 }
 
@@ -73,18 +73,17 @@ where
         [RateType::NoCash, RateType::Cash].contains(&rate_type),
         Error::InvalidRateType
     );
-    let resp = client
-        .get(
-            config
-                .rates_url()
-                .replace("%d", &(rate_type as u8).to_string()),
-        )
-        .header(reqwest::header::USER_AGENT, USER_AGENT)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<T1>()
-        .await?;
+    let body = http::send(
+        client
+            .get(
+                config
+                    .rates_url()
+                    .replace("%d", &(rate_type as u8).to_string()),
+            )
+            .header(reqwest::header::USER_AGENT, USER_AGENT),
+    )
+    .await?;
+    let resp = serde_json::from_str(&body)?;
     Ok(resp)
 }
 
@@ -151,12 +150,102 @@ impl Config {
             Source::Vtb => self.vtb.enabled,
         }
     }
+
+    pub fn options_for(&self, src: Source) -> &Options {
+        match src {
+            Source::Acba => &self.acba.options,
+            Source::AEB => &self.aeb.options,
+            Source::Ameria => &self.ameria.options,
+            Source::Amio => &self.amio.options,
+            Source::Ararat => &self.ararat.options,
+            Source::Ardshin => &self.ardshin.options,
+            Source::ArmSwiss => &self.armswiss.options,
+            Source::Artsakh => &self.artsakh.options,
+            Source::Avosend => &self.avosend.options,
+            Source::Byblos => &self.byblos.options,
+            Source::Cb => &self.cb.options,
+            Source::Converse => &self.converse.options,
+            Source::Evoca => &self.evoca.options,
+            Source::Fast => &self.fast.options,
+            Source::IdBank => &self.idbank.options,
+            Source::IdPay => &self.idpay.options,
+            Source::Ineco => &self.ineco.options,
+            Source::Kwikpay => &self.kwikpay.options,
+            Source::Mellat => &self.mellat.options,
+            Source::Mir => &self.mir.options,
+            #[cfg(feature = "moex")]
+            Source::MOEX => &self.moex.options,
+            Source::SAS => &self.sas.options,
+            Source::Unibank => &self.unibank.options,
+            Source::UnionPay => &self.unionpay.options,
+            Source::Unistream => &self.unistream.options,
+            Source::Vtb => &self.vtb.options,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct BaseConfig {
     pub rates_url: String,
     pub enabled: bool,
+    #[serde(flatten)]
+    pub options: Options,
+}
+
+/// Per source overrides of the `[bot]` collection settings.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Options {
+    pub update_interval: Option<u64>,
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub fees: Vec<Fee>,
+}
+
+/// Commission a source charges on a conversion step, tiers are expressed as
+/// several fees with different `above`.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct Fee {
+    /// Currency sold, any if not set.
+    #[serde(default, deserialize_with = "de::option_currency")]
+    pub from: Option<Currency>,
+    /// Currency bought, any if not set.
+    #[serde(default, deserialize_with = "de::option_currency")]
+    pub to: Option<Currency>,
+    /// Smallest amount the fee applies to.
+    #[serde(default)]
+    pub above: Decimal,
+    #[serde(default)]
+    pub percent: Decimal,
+    #[serde(default)]
+    pub fixed: Decimal,
+    pub min: Option<Decimal>,
+    pub max: Option<Decimal>,
+    /// Currency of `above`, `fixed`, `min` and `max`, the sold one if not set.
+    #[serde(default, deserialize_with = "de::option_currency")]
+    pub currency: Option<Currency>,
+}
+
+impl Fee {
+    pub fn is_for(&self, from: &Currency, to: &Currency) -> bool {
+        self.from.as_ref().is_none_or(|v| v == from) && self.to.as_ref().is_none_or(|v| v == to)
+    }
+
+    /// Fee for `amount` given in the fee currency, without an amount only the
+    /// percentage can be charged.
+    pub fn charge(&self, amount: Decimal, is_amount: bool) -> Decimal {
+        let mut fee = percent(self.percent, amount);
+        if !is_amount {
+            return fee;
+        }
+        fee += self.fixed;
+        if let Some(min) = self.min {
+            fee = fee.max(min);
+        }
+        if let Some(max) = self.max {
+            fee = fee.min(max);
+        }
+        fee.min(amount)
+    }
 }
 
 impl BaseConfigTrait for BaseConfig {
@@ -169,7 +258,7 @@ fn percent(value: Decimal, from: Decimal) -> Decimal {
     value / Decimal::ONE_HUNDRED * from
 }
 
-mod de {
+pub(crate) mod de {
     use super::{Currency, RateType};
     use rust_decimal::Decimal;
     use serde::{de, Deserialize, Deserializer};
@@ -183,6 +272,14 @@ mod de {
         Ok(Currency::new(s))
     }
 
+    pub fn option_currency<'de, D>(deserializer: D) -> Result<Option<Currency>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = Option::<String>::deserialize(deserializer)?;
+        Ok(s.map(Currency::new))
+    }
+
     pub fn rate_type<'de, D>(deserializer: D) -> Result<RateType, D::Error>
     where
         D: Deserializer<'de>,
@@ -227,6 +324,7 @@ mod de {
     PartialOrd,
     strum::EnumString,
     strum::EnumCount,
+    serde::Serialize,
 )]
 #[strum(ascii_case_insensitive)]
 pub enum Source {
@@ -285,9 +383,20 @@ impl Source {
         ]
         .contains(self)
     }
+
+    /// Source whose upstream response this one is derived from.
+    pub fn parent(&self) -> Option<Self> {
+        match self {
+            Self::Kwikpay | Self::Unistream => Some(Self::Unibank),
+            Self::IdPay => Some(Self::IdBank),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Eq, Hash, derive_more::Display)]
+#[derive(
+    Debug, PartialEq, Clone, Eq, Hash, derive_more::Display, serde::Serialize, Deserialize,
+)]
 pub struct Currency(pub String);
 
 impl Currency {
@@ -323,7 +432,16 @@ impl Default for Currency {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, strum::EnumString)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    strum::EnumString,
+    strum::FromRepr,
+    serde_repr::Serialize_repr,
+    serde_repr::Deserialize_repr,
+)]
 #[strum(ascii_case_insensitive)]
 #[repr(u8)]
 pub enum RateType {
@@ -349,9 +467,11 @@ pub enum Error {
     Html,
     #[error("no rates found")]
     NoRates,
+    #[error("no shared response")]
+    NoShared,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Rate {
     pub from: Currency,
     pub to: Currency,
@@ -374,7 +494,10 @@ pub async fn collect(
         Source::Evoca => evoca::collect(client, &config.evoca).await?,
         Source::Fast => fast::collect(client, &config.fast).await?,
         Source::Ineco => ineco::collect(client, &config.ineco).await?,
-        Source::Kwikpay => kwikpay::collect(client, &config.kwikpay).await?,
+        Source::Kwikpay | Source::Unistream | Source::IdPay => {
+            let parent = src.parent().expect("panic");
+            derive(&fetch_shared(client, config, parent).await?, config, src)?
+        }
         Source::Mellat => mellat::collect(client, &config.mellat).await?,
         Source::Converse => converse::collect(client, &config.converse).await?,
         Source::AEB => aeb::collect(client, &config.aeb).await?,
@@ -385,14 +508,44 @@ pub async fn collect(
         Source::Byblos => byblos::collect(client, &config.byblos).await?,
         Source::IdBank => idbank::collect(client, &config.idbank).await?,
         Source::Ararat => ararat::collect(client, &config.ararat).await?,
-        Source::IdPay => idpay::collect(client, &config.idpay).await?,
         Source::Mir => mir::collect(client, &config.mir).await?,
         #[cfg(feature = "moex")]
         Source::MOEX => moex::collect(client, &config.moex).await?,
         Source::SAS => sas::collect(client, &config.sas).await?,
         Source::Avosend => avosend::collect(client, &config.avosend).await?,
         Source::UnionPay => unionpay::collect(client, &config.unionpay).await?,
-        Source::Unistream => unistream::collect(client, &config.unistream).await?,
+    };
+    Ok(rates)
+}
+
+/// Upstream response shared by a parent source and the sources derived from it.
+#[derive(Debug)]
+pub enum Shared {
+    Unibank(Vec<Rate>),
+    IdBank(idbank::Response),
+}
+
+pub async fn fetch_shared(
+    client: &reqwest::Client,
+    config: &Config,
+    src: Source,
+) -> anyhow::Result<Shared> {
+    let shared = match src {
+        Source::Unibank => Shared::Unibank(unibank::collect(client, &config.unibank).await?),
+        Source::IdBank => Shared::IdBank(idbank::post(client, &config.idbank).await?),
+        _ => bail!(Error::NoShared),
+    };
+    Ok(shared)
+}
+
+pub fn derive(shared: &Shared, config: &Config, src: Source) -> anyhow::Result<Vec<Rate>> {
+    let rates = match (shared, src) {
+        (Shared::Unibank(rates), Source::Unibank) => rates.clone(),
+        (Shared::Unibank(rates), Source::Kwikpay) => kwikpay::parse(rates, &config.kwikpay)?,
+        (Shared::Unibank(rates), Source::Unistream) => unistream::parse(rates, &config.unistream)?,
+        (Shared::IdBank(resp), Source::IdBank) => idbank::parse(resp),
+        (Shared::IdBank(resp), Source::IdPay) => idpay::parse(resp, &config.idpay)?,
+        _ => bail!(Error::NoShared),
     };
     Ok(rates)
 }
@@ -402,6 +555,7 @@ mod tests {
     use super::*;
     use crate::config::Config;
     use std::{sync::LazyLock, time::Duration};
+    use strum::IntoEnumIterator;
 
     static CFG: LazyLock<Config> =
         LazyLock::new(|| toml::from_str(include_str!("../../config/config.toml")).unwrap());
@@ -412,6 +566,53 @@ mod tests {
             .build()
     }
 
+    #[test]
+    fn test_options() {
+        assert_eq!(CFG.src.options_for(Source::Cb).update_interval, Some(3600));
+        assert_eq!(CFG.src.options_for(Source::Acba).update_interval, None);
+        assert_eq!(CFG.src.options_for(Source::Acba).timeout, None);
+        let cfg: BaseConfig = toml::from_str(
+            r#"
+            rates_url = ""
+            enabled = true
+
+            [[fees]]
+            from = "rur"
+            percent = 1.5
+
+            [[fees]]
+            above = 1000
+            fixed = 500
+            currency = "AMD"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.options.fees.len(), 2);
+        assert_eq!(cfg.options.fees[0].from, Some(Currency::rub()));
+        assert_eq!(cfg.options.fees[0].percent, Decimal::new(15, 1));
+        assert_eq!(cfg.options.fees[1].currency, Some(Currency::default()));
+        assert!(cfg.options.fees[1].is_for(&Currency::usd(), &Currency::eur()));
+    }
+
+    #[test]
+    fn test_derive() -> anyhow::Result<()> {
+        let shared = Shared::Unibank(vec![Rate {
+            from: Currency::rub(),
+            to: Currency::default(),
+            rate_type: RateType::Cash,
+            buy: Some(Decimal::new(4, 0)),
+            sell: Some(Decimal::new(5, 0)),
+        }]);
+        for src in Source::iter().filter(|v| v.parent() == Some(Source::Unibank)) {
+            let rates = derive(&shared, &CFG.src, src)?;
+            assert!(!rates.is_empty());
+            assert!(rates.iter().all(|v| v.buy < Some(Decimal::new(4, 0))));
+        }
+        assert_eq!(derive(&shared, &CFG.src, Source::Unibank)?.len(), 1);
+        assert!(derive(&shared, &CFG.src, Source::IdPay).is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_acba() -> anyhow::Result<()> {
         let client = build_client(&CFG)?;
@@ -1,4 +1,4 @@
-use crate::source::{Currency as ModCurrency, Rate, RateType};
+use crate::source::{http, Currency as ModCurrency, Options, Rate, RateType};
 use anyhow::bail;
 use rust_decimal::Decimal;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -77,6 +77,8 @@ pub struct Config {
     pub path_order_book: String,
     pub path_currency: String,
     pub enabled: bool,
+    #[serde(flatten)]
+    pub options: Options,
     pub req: GetOrderBookRequest,
 }
 
@@ -123,24 +125,27 @@ where
     if TOKEN.is_empty() {
         bail!(env::VarError::NotPresent);
     }
-    let resp = client
-        .post(format!("{}/{}", base_url, url_path))
-        .json(req_data)
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("Bearer {}", TOKEN.to_string()),
-        )
-        .send()
-        .await?
-        .error_for_status()?
-        .json()
-        .await?;
+    let body = http::send(
+        client
+            .post(format!("{}/{}", base_url, url_path))
+            .json(req_data)
+            .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", *TOKEN)),
+    )
+    .await?;
+    let resp = serde_json::from_str(&body)?;
     Ok(resp)
 }
 
 pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
     let currency: CurrencyResponse = get_currency(client, config).await?;
     let order_book: GetOrderBookResponse = get_order_book(client, config).await?;
+    parse(&currency, &order_book)
+}
+
+pub(crate) fn parse(
+    currency: &CurrencyResponse,
+    order_book: &GetOrderBookResponse,
+) -> anyhow::Result<Vec<Rate>> {
     let to_decimal = |units: &String, nano: i32| format!("{}.{}", units, nano).parse::<Decimal>();
     let mut rate_buy = None;
     let mut rate_sell = None;
@@ -172,3 +177,28 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     }
     Ok(rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let currency: CurrencyResponse =
+            serde_json::from_str(include_str!("../../fixtures/moex_currency.json"))?;
+        let order_book: GetOrderBookResponse =
+            serde_json::from_str(include_str!("../../fixtures/moex_order_book.json"))?;
+        let rates = parse(&currency, &order_book)?;
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].from, ModCurrency::rub());
+        assert_eq!(rates[0].buy, Some(dec!(100) / dec!(20.6)));
+        assert_eq!(rates[0].sell, Some(dec!(100) / dec!(20.4)));
+        let order_book = GetOrderBookResponse {
+            bids: vec![],
+            asks: vec![],
+        };
+        assert!(parse(&currency, &order_book)?.is_empty());
+        Ok(())
+    }
+}
@@ -1,19 +1,17 @@
+use crate::source::{http, BaseConfigTrait, Currency, Error, Rate, RateType};
 pub use crate::source::{BaseConfig as Config, BaseResponse as Response};
-use crate::source::{BaseConfigTrait, Currency, Error, Rate, RateType};
 use select::{document::Document, predicate::Class};
 
 async fn get<T>(client: &reqwest::Client, config: &T) -> anyhow::Result<Response>
 where
     T: BaseConfigTrait,
 {
-    let html = client
-        .get(config.rates_url())
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
-    let document = Document::from(html.as_str());
+    let html = http::send(client.get(config.rates_url())).await?;
+    parse(&html)
+}
+
+pub(crate) fn parse(html: &str) -> anyhow::Result<Response> {
+    let document = Document::from(html);
     let exchange_table = document
         .find(Class("exchange-table"))
         .next()
@@ -43,3 +41,20 @@ where
     let resp: Response = get(client, config).await?;
     Ok(resp.rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp = parse(include_str!("../../fixtures/sas.html"))?;
+        assert_eq!(resp.rates.len(), 2);
+        assert_eq!(resp.rates[0].from, Currency::usd());
+        assert_eq!(resp.rates[0].rate_type, RateType::Cash);
+        assert_eq!(resp.rates[1].buy, Some(dec!(4.19)));
+        assert!(parse("<html></html>").is_err());
+        Ok(())
+    }
+}
@@ -35,13 +35,16 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
         .rates_url
         .replace("%s", &date.format("%Y%m%d").to_string());
     let resp: Response = get_json(client, &config).await?;
+    parse(resp)
+}
+
+pub(crate) fn parse(resp: Response) -> anyhow::Result<Vec<Rate>> {
     let from = Currency::usd();
     let to = Currency::rub();
     let Some(rate) = resp
         .exchange_rate_json
         .iter()
-        .filter(|v| v.base_cur == from && v.trans_cur == to)
-        .next()
+        .find(|v| v.base_cur == from && v.trans_cur == to)
     else {
         bail!(Error::NoRates);
     };
@@ -59,3 +62,21 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     };
     Ok(vec![new_rate(RateType::NoCash), new_rate(RateType::Cash)])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: Response = serde_json::from_str(include_str!("../../fixtures/unionpay.json"))?;
+        let rates = parse(resp)?;
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].from, Currency::usd());
+        assert_eq!(rates[0].to, Currency::rub());
+        assert_eq!(rates[0].buy, Some(Decimal::ONE / dec!(0.0108)));
+        assert_eq!(rates[0].sell, None);
+        Ok(())
+    }
+}
@@ -1,30 +1,22 @@
-pub use crate::source::unibank::Response;
-use crate::source::{percent, unibank, BaseConfigTrait, Currency, Error, Rate, RateType};
+use crate::source::{percent, Currency, Error, Options, Rate, RateType};
 use anyhow::bail;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
-    pub rates_url: String,
     pub enabled: bool,
+    #[serde(flatten)]
+    pub options: Options,
     pub commission_rate_from_bank: Decimal,
     pub commission_rate_from_any_card: Decimal,
 }
 
-impl BaseConfigTrait for Config {
-    fn rates_url(&self) -> &str {
-        &self.rates_url
-    }
-}
-
-pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Result<Vec<Rate>> {
-    let rates = unibank::collect(client, config).await?;
+pub(crate) fn parse(rates: &[Rate], config: &Config) -> anyhow::Result<Vec<Rate>> {
     let from = Currency::rub();
     let Some(rate) = rates
         .iter()
-        .filter(|v| v.rate_type == RateType::Cash && v.from == from)
-        .next()
+        .find(|v| v.rate_type == RateType::Cash && v.from == from)
     else {
         bail!(Error::NoRates);
     };
@@ -46,3 +38,27 @@ pub async fn collect(client: &reqwest::Client, config: &Config) -> anyhow::Resul
     .collect();
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::lsoft;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp: lsoft::Response =
+            quick_xml::de::from_str(include_str!("../../fixtures/lsoft.xml"))?;
+        let config = Config {
+            enabled: true,
+            options: Options::default(),
+            commission_rate_from_bank: dec!(1),
+            commission_rate_from_any_card: dec!(2),
+        };
+        let rates = parse(&lsoft::parse(resp)?, &config)?;
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].buy, Some(dec!(4.1382)));
+        assert_eq!(rates[1].buy, Some(dec!(4.0964)));
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
+use crate::source::{http, BaseConfigTrait, Currency, Error, Rate, RateType};
 pub use crate::source::{BaseConfig as Config, BaseResponse as Response};
-use crate::source::{BaseConfigTrait, Currency, Error, Rate, RateType};
 use select::{
     document::Document,
     predicate::{Class, Name},
@@ -9,14 +9,12 @@ pub async fn get<T>(client: &reqwest::Client, config: &T) -> anyhow::Result<Resp
 where
     T: BaseConfigTrait,
 {
-    let html = client
-        .get(config.rates_url())
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
-    let document = Document::from(html.as_str());
+    let html = http::send(client.get(config.rates_url())).await?;
+    parse(&html)
+}
+
+pub(crate) fn parse(html: &str) -> anyhow::Result<Response> {
+    let document = Document::from(html);
     let mut rates = vec![];
     for (idx, exchange_table) in document.find(Class("exchange-rate-table")).enumerate() {
         for row in exchange_table
@@ -73,3 +71,22 @@ where
     let resp: Response = get(client, config).await?;
     Ok(resp.rates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let resp = parse(include_str!("../../fixtures/vtb.html"))?;
+        assert_eq!(resp.rates.len(), 4);
+        assert_eq!(resp.rates[0].rate_type, RateType::Cash);
+        assert_eq!(resp.rates[0].sell, Some(dec!(392)));
+        assert_eq!(resp.rates[3].rate_type, RateType::NoCash);
+        assert_eq!(resp.rates[3].from, Currency::rub());
+        assert_eq!(resp.rates[3].buy, Some(dec!(4.2)));
+        assert_eq!(resp.rates[3].sell, None);
+        Ok(())
+    }
+}
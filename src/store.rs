@@ -0,0 +1,572 @@
+use crate::{
+    alert::{Alert, Op},
+    settings::Settings,
+    source::{Currency, Rate, RateType, Source},
+};
+use anyhow::anyhow;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use std::{
+    collections::HashMap,
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS rates (
+        src TEXT NOT NULL,
+        ts INTEGER NOT NULL,
+        rate_type INTEGER NOT NULL,
+        from_cur TEXT NOT NULL,
+        to_cur TEXT NOT NULL,
+        buy TEXT,
+        sell TEXT
+    );
+    CREATE INDEX IF NOT EXISTS rates_src_ts ON rates (src, ts);
+    CREATE INDEX IF NOT EXISTS rates_pair_ts ON rates (from_cur, to_cur, ts);
+    CREATE INDEX IF NOT EXISTS rates_ts ON rates (ts);
+    CREATE TABLE IF NOT EXISTS alerts (
+        id INTEGER PRIMARY KEY,
+        chat_id INTEGER NOT NULL,
+        from_cur TEXT NOT NULL,
+        to_cur TEXT NOT NULL,
+        op TEXT NOT NULL,
+        value TEXT NOT NULL,
+        triggered INTEGER NOT NULL DEFAULT 0,
+        rate_type INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS alerts_chat_id ON alerts (chat_id);
+    CREATE TABLE IF NOT EXISTS digests (
+        chat_id INTEGER PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        user_id INTEGER PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+#[derive(Debug)]
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn append(&mut self, src: Source, ts: SystemTime, rates: &[Rate]) -> anyhow::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO rates (src, ts, rate_type, from_cur, to_cur, buy, sell)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            let ts = to_unix(ts);
+            for rate in rates {
+                stmt.execute(params![
+                    src.to_string(),
+                    ts,
+                    rate.rate_type as u8,
+                    rate.from.0,
+                    rate.to.0,
+                    rate.buy.map(|v| v.to_string()),
+                    rate.sell.map(|v| v.to_string()),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes the rates stored before `before`, returns their number.
+    pub fn prune(&mut self, before: SystemTime) -> anyhow::Result<usize> {
+        let n = self
+            .conn
+            .execute("DELETE FROM rates WHERE ts < ?1", params![to_unix(before)])?;
+        Ok(n)
+    }
+
+    pub fn get_rates(
+        &self,
+        src: Source,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> anyhow::Result<Vec<(SystemTime, Rate)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT ts, rate_type, from_cur, to_cur, buy, sell FROM rates
+            WHERE src = ?1 AND ts >= ?2 AND ts <= ?3
+            ORDER BY ts",
+        )?;
+        let rows = stmt.query_map(
+            params![src.to_string(), to_unix(since), to_unix(until)],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    (
+                        row.get::<_, u8>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ),
+                ))
+            },
+        )?;
+        let mut results = vec![];
+        for row in rows {
+            let (ts, raw) = row?;
+            results.push((from_unix(ts), to_rate(raw)?));
+        }
+        Ok(results)
+    }
+
+    /// Rates of the pair in the range from every source, or only from `src`.
+    pub fn get_pair_rates(
+        &self,
+        src: Option<Source>,
+        from: &Currency,
+        to: &Currency,
+        rate_type: RateType,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> anyhow::Result<Vec<(Source, SystemTime, Rate)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT src, ts, rate_type, from_cur, to_cur, buy, sell FROM rates
+            WHERE from_cur = ?1 AND to_cur = ?2 AND rate_type = ?3
+            AND ts >= ?4 AND ts <= ?5 AND (?6 IS NULL OR src = ?6)
+            ORDER BY ts",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                from.0,
+                to.0,
+                rate_type as u8,
+                to_unix(since),
+                to_unix(until),
+                src.map(|v| v.to_string()),
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    (
+                        row.get::<_, u8>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                    ),
+                ))
+            },
+        )?;
+        let mut results = vec![];
+        for row in rows {
+            let (src, ts, raw) = row?;
+            let Ok(src) = Source::from_str(&src) else {
+                continue;
+            };
+            results.push((src, from_unix(ts), to_rate(raw)?));
+        }
+        Ok(results)
+    }
+
+    pub fn get_latest(&self) -> anyhow::Result<HashMap<Source, (SystemTime, Vec<Rate>)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT r.src, r.ts, r.rate_type, r.from_cur, r.to_cur, r.buy, r.sell FROM rates r
+            JOIN (SELECT src, MAX(ts) AS ts FROM rates GROUP BY src) l
+            ON r.src = l.src AND r.ts = l.ts",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                (
+                    row.get::<_, u8>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ),
+            ))
+        })?;
+        let mut results: HashMap<Source, (SystemTime, Vec<Rate>)> = HashMap::new();
+        for row in rows {
+            let (src, ts, raw) = row?;
+            let Ok(src) = Source::from_str(&src) else {
+                continue;
+            };
+            results
+                .entry(src)
+                .or_insert_with(|| (from_unix(ts), vec![]))
+                .1
+                .push(to_rate(raw)?);
+        }
+        Ok(results)
+    }
+
+    pub fn add_alert(
+        &mut self,
+        chat_id: i64,
+        from: &Currency,
+        to: &Currency,
+        op: Op,
+        value: Decimal,
+        rate_type: RateType,
+    ) -> anyhow::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO alerts (chat_id, from_cur, to_cur, op, value, rate_type)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                chat_id,
+                from.0,
+                to.0,
+                op.to_string(),
+                value.to_string(),
+                rate_type as u8,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Alerts of the chat, or of every chat without one.
+    pub fn get_alerts(&self, chat_id: Option<i64>) -> anyhow::Result<Vec<Alert>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, chat_id, from_cur, to_cur, op, value, triggered, rate_type FROM alerts
+            WHERE ?1 IS NULL OR chat_id = ?1
+            ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, bool>(6)?,
+                row.get::<_, u8>(7)?,
+            ))
+        })?;
+        let mut results = vec![];
+        for row in rows {
+            let (id, chat_id, from, to, op, value, triggered, rate_type) = row?;
+            results.push(Alert {
+                id,
+                chat_id,
+                from: Currency::new(from),
+                to: Currency::new(to),
+                op: Op::from_str(&op)?,
+                value: Decimal::from_str(&value)?,
+                rate_type: RateType::from_repr(rate_type)
+                    .ok_or_else(|| anyhow!("invalid rate type: {rate_type}"))?,
+                triggered,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Whether the chat had the alert.
+    pub fn delete_alert(&mut self, chat_id: i64, id: i64) -> anyhow::Result<bool> {
+        let n = self.conn.execute(
+            "DELETE FROM alerts WHERE chat_id = ?1 AND id = ?2",
+            params![chat_id, id],
+        )?;
+        Ok(n > 0)
+    }
+
+    pub fn set_alert_triggered(&mut self, id: i64, triggered: bool) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE alerts SET triggered = ?2 WHERE id = ?1",
+            params![id, triggered],
+        )?;
+        Ok(())
+    }
+
+    /// Whether the chat was not subscribed yet.
+    pub fn add_digest(&mut self, chat_id: i64) -> anyhow::Result<bool> {
+        let n = self.conn.execute(
+            "INSERT OR IGNORE INTO digests (chat_id) VALUES (?1)",
+            params![chat_id],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Whether the chat was subscribed.
+    pub fn delete_digest(&mut self, chat_id: i64) -> anyhow::Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM digests WHERE chat_id = ?1", params![chat_id])?;
+        Ok(n > 0)
+    }
+
+    pub fn get_digests(&self) -> anyhow::Result<Vec<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT chat_id FROM digests ORDER BY chat_id")?;
+        let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Settings of the user, default if never saved.
+    pub fn get_settings(&self, user_id: i64) -> anyhow::Result<Settings> {
+        let value = self
+            .conn
+            .query_row(
+                "SELECT value FROM settings WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        match value {
+            Some(v) => Ok(serde_json::from_str(&v)?),
+            None => Ok(Settings::default()),
+        }
+    }
+
+    pub fn set_settings(&mut self, user_id: i64, settings: &Settings) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO settings (user_id, value) VALUES (?1, ?2)
+            ON CONFLICT (user_id) DO UPDATE SET value = excluded.value",
+            params![user_id, serde_json::to_string(settings)?],
+        )?;
+        Ok(())
+    }
+}
+
+type RawRate = (u8, String, String, Option<String>, Option<String>);
+
+fn to_rate((rate_type, from, to, buy, sell): RawRate) -> anyhow::Result<Rate> {
+    Ok(Rate {
+        from: Currency::new(from),
+        to: Currency::new(to),
+        rate_type: RateType::from_repr(rate_type)
+            .ok_or_else(|| anyhow!("invalid rate type: {rate_type}"))?,
+        buy: buy.as_deref().map(Decimal::from_str).transpose()?,
+        sell: sell.as_deref().map(Decimal::from_str).transpose()?,
+    })
+}
+
+fn to_unix(ts: SystemTime) -> i64 {
+    ts.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
+
+fn from_unix(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_append_get_rates() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        let now = SystemTime::now();
+        let rates = vec![
+            Rate {
+                from: Currency::usd(),
+                to: Currency::default(),
+                rate_type: RateType::NoCash,
+                buy: Some(dec!(387.5)),
+                sell: Some(dec!(391)),
+            },
+            Rate {
+                from: Currency::rub(),
+                to: Currency::default(),
+                rate_type: RateType::Cash,
+                buy: Some(dec!(4.21)),
+                sell: None,
+            },
+        ];
+        store.append(Source::Acba, now, &rates)?;
+        store.append(Source::Ameria, now, &rates[..1])?;
+        let day = Duration::from_secs(24 * 60 * 60);
+        let results = store.get_rates(Source::Acba, now - day, now + day)?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1.buy, Some(dec!(387.5)));
+        assert_eq!(results[1].1.rate_type, RateType::Cash);
+        assert_eq!(results[1].1.sell, None);
+        assert!(store
+            .get_rates(Source::Acba, now + day, now + day * 2)?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let rates = [Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(dec!(386)),
+            sell: None,
+        }];
+        store.append(Source::Acba, now - day * 3, &rates)?;
+        store.append(Source::Acba, now - day, &rates)?;
+        store.append(Source::Ameria, now - day * 3, &rates)?;
+        assert_eq!(store.prune(now - day * 2)?, 2);
+        assert_eq!(store.prune(now - day * 2)?, 0);
+        let results = store.get_rates(Source::Acba, now - day * 4, now)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            store.get_latest()?.keys().collect::<Vec<_>>(),
+            [&Source::Acba]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_pair_rates() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        let now = SystemTime::now();
+        let rate = |from, rate_type, buy| Rate {
+            from,
+            to: Currency::default(),
+            rate_type,
+            buy: Some(buy),
+            sell: None,
+        };
+        store.append(
+            Source::Acba,
+            now,
+            &[
+                rate(Currency::usd(), RateType::NoCash, dec!(386)),
+                rate(Currency::usd(), RateType::Cash, dec!(385)),
+                rate(Currency::rub(), RateType::NoCash, dec!(4.6)),
+            ],
+        )?;
+        store.append(
+            Source::Ameria,
+            now,
+            &[rate(Currency::usd(), RateType::NoCash, dec!(387))],
+        )?;
+        let day = Duration::from_secs(24 * 60 * 60);
+        let get = |src| {
+            store.get_pair_rates(
+                src,
+                &Currency::usd(),
+                &Currency::default(),
+                RateType::NoCash,
+                now - day,
+                now + day,
+            )
+        };
+        let results = get(None)?;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|v| v.2.from == Currency::usd()));
+        let results = get(Some(Source::Ameria))?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, Source::Ameria);
+        assert_eq!(results[0].2.buy, Some(dec!(387)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_latest() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        let now = SystemTime::now();
+        let rate = |buy| Rate {
+            from: Currency::usd(),
+            to: Currency::default(),
+            rate_type: RateType::NoCash,
+            buy: Some(buy),
+            sell: None,
+        };
+        store.append(
+            Source::Acba,
+            now - Duration::from_secs(300),
+            &[rate(dec!(386))],
+        )?;
+        store.append(Source::Acba, now, &[rate(dec!(387)), rate(dec!(388))])?;
+        store.append(Source::Cb, now, &[rate(dec!(389))])?;
+        let latest = store.get_latest()?;
+        assert_eq!(latest.len(), 2);
+        let (ts, rates) = &latest[&Source::Acba];
+        assert_eq!(*ts, from_unix(to_unix(now)));
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].buy, Some(dec!(387)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_alerts() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        let id1 = store.add_alert(
+            1,
+            &Currency::rub(),
+            &Currency::default(),
+            Op::Above,
+            dec!(4.9),
+            RateType::NoCash,
+        )?;
+        let id2 = store.add_alert(
+            2,
+            &Currency::usd(),
+            &Currency::default(),
+            Op::Below,
+            dec!(380),
+            RateType::Cash,
+        )?;
+        let alerts = store.get_alerts(Some(1))?;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].id, id1);
+        assert_eq!(alerts[0].from, Currency::rub());
+        assert_eq!(alerts[0].op, Op::Above);
+        assert_eq!(alerts[0].value, dec!(4.9));
+        assert!(!alerts[0].triggered);
+        store.set_alert_triggered(id2, true)?;
+        let alerts = store.get_alerts(None)?;
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts[1].triggered);
+        assert_eq!(alerts[1].rate_type, RateType::Cash);
+        assert!(!store.delete_alert(1, id2)?);
+        assert!(store.delete_alert(2, id2)?);
+        assert_eq!(store.get_alerts(None)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_digests() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        assert!(store.add_digest(2)?);
+        assert!(store.add_digest(1)?);
+        assert!(!store.add_digest(1)?);
+        assert_eq!(store.get_digests()?, vec![1, 2]);
+        assert!(store.delete_digest(2)?);
+        assert!(!store.delete_digest(2)?);
+        assert_eq!(store.get_digests()?, vec![1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings() -> anyhow::Result<()> {
+        let mut store = Store::open_in_memory()?;
+        assert_eq!(store.get_settings(1)?, Settings::default());
+        let mut settings = Settings {
+            rate_type: Some(RateType::Cash),
+            ..Default::default()
+        };
+        store.set_settings(1, &settings)?;
+        settings.rate_dp = Some(2);
+        store.set_settings(1, &settings)?;
+        assert_eq!(store.get_settings(1)?, settings);
+        assert_eq!(store.get_settings(2)?, Settings::default());
+        Ok(())
+    }
+}
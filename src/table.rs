@@ -0,0 +1,313 @@
+//! Typed results of the `generate` tables and their renderers.
+
+use crate::source::{Currency, RateType, Source};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Serialize, Serializer};
+use std::{
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use teloxide::utils::{html, markdown};
+
+/// Decimal places of amounts.
+pub const AMOUNT_DP: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Format {
+    Text,
+    /// Telegram HTML.
+    Html,
+    /// Telegram MarkdownV2.
+    Markdown,
+    Json,
+}
+
+/// Conversion rates of `from` in `to` from every source, best first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvTable {
+    pub from: Currency,
+    pub to: Currency,
+    pub amount: Option<Decimal>,
+    pub rate_type: RateType,
+    pub inv: bool,
+    pub rows: Vec<ConvRow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvRow {
+    pub src: Source,
+    pub rate_type: RateType,
+    pub rate: Decimal,
+    /// Percent versus the best bank, or the loss in AMD with an amount.
+    pub diff: Option<Decimal>,
+    /// Amount of `to` received for the amount of the table.
+    pub received: Decimal,
+    pub path: Vec<Currency>,
+    #[serde(serialize_with = "unix")]
+    pub updated_at: Option<SystemTime>,
+}
+
+/// Rates quoted by a source.
+#[derive(Debug, Clone, Serialize)]
+pub struct SrcTable {
+    pub src: Source,
+    pub rate_type: RateType,
+    #[serde(serialize_with = "unix")]
+    pub updated_at: Option<SystemTime>,
+    pub rows: Vec<SrcRow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SrcRow {
+    pub from: Currency,
+    pub to: Currency,
+    pub buy: Option<Decimal>,
+    pub sell: Option<Decimal>,
+}
+
+impl ConvTable {
+    /// `rate_dp` and `diff_dp` are the decimal places of the text formats,
+    /// amounts have `AMOUNT_DP`.
+    pub fn render(&self, format: Format, rate_dp: u32, diff_dp: u32) -> String {
+        match format {
+            Format::Text => self.to_text(rate_dp, diff_dp),
+            Format::Html => wrap(self.to_text(rate_dp, diff_dp), html::code_block),
+            Format::Markdown => wrap(self.to_text(rate_dp, diff_dp), markdown::code_block),
+            Format::Json => to_json(self),
+        }
+    }
+
+    /// Value and diff of every row, the received amount with an amount and
+    /// the rate without.
+    pub fn cells(&self, rate_dp: u32, diff_dp: u32) -> Vec<(String, String)> {
+        let (value_dp, diff_dp) = match self.amount {
+            Some(_) => (AMOUNT_DP, AMOUNT_DP),
+            None => (rate_dp, diff_dp),
+        };
+        self.rows
+            .iter()
+            .map(|v| {
+                let value = match self.amount {
+                    Some(_) => v.received,
+                    None => v.rate,
+                };
+                let diff = v
+                    .diff
+                    .map(|v| decimal_to_string(v, diff_dp))
+                    .unwrap_or_default();
+                (decimal_to_string(value, value_dp), diff)
+            })
+            .collect()
+    }
+
+    fn to_text(&self, rate_dp: u32, diff_dp: u32) -> String {
+        let cells = self.cells(rate_dp, diff_dp);
+        let src_width = self
+            .rows
+            .iter()
+            .map(|v| v.src.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let value_width = cells.iter().map(|v| v.0.len()).max().unwrap_or(0);
+        let diff_width = cells.iter().map(|v| v.1.len()).max().unwrap_or(0);
+        let mut s = String::new();
+        for (row, (value, diff)) in self.rows.iter().zip(cells) {
+            writeln!(
+                &mut s,
+                "{} {:<src_width$} | {value:<value_width$} | {diff:>diff_width$} | {}",
+                row.src.prefix(),
+                row.src,
+                path_to_string(&row.path),
+            )
+            .unwrap();
+        }
+        s
+    }
+}
+
+impl SrcTable {
+    pub fn render(&self, format: Format, rate_dp: u32) -> String {
+        match format {
+            Format::Text => self.to_text(rate_dp),
+            Format::Html => wrap(self.to_text(rate_dp), html::code_inline),
+            Format::Markdown => wrap(self.to_text(rate_dp), markdown::code_inline),
+            Format::Json => to_json(self),
+        }
+    }
+
+    fn to_text(&self, rate_dp: u32) -> String {
+        const NO_RATE: &str = "-";
+        let cell = |v: Option<Decimal>| match v {
+            Some(v) => decimal_to_string(v, rate_dp),
+            None => NO_RATE.into(),
+        };
+        let cells = self
+            .rows
+            .iter()
+            .map(|v| (cell(v.buy), cell(v.sell)))
+            .collect::<Vec<_>>();
+        let buy_width = cells.iter().map(|v| v.0.len()).max().unwrap_or(0);
+        let sell_width = cells.iter().map(|v| v.1.len()).max().unwrap_or(0);
+        let mut s = String::new();
+        for (row, (buy, sell)) in self.rows.iter().zip(cells) {
+            writeln!(
+                &mut s,
+                "{buy:<buy_width$} | {sell:<sell_width$} | {}/{}",
+                row.from, row.to,
+            )
+            .unwrap();
+        }
+        s
+    }
+}
+
+pub fn decimal_to_string(value: Decimal, dp: u32) -> String {
+    value
+        .round_dp_with_strategy(dp, RoundingStrategy::MidpointAwayFromZero)
+        .normalize()
+        .to_string()
+}
+
+pub fn path_to_string(path: &[Currency]) -> String {
+    path.iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Empty tables stay empty, callers treat them as no data.
+fn wrap(s: String, f: fn(&str) -> String) -> String {
+    if s.is_empty() {
+        s
+    } else {
+        f(&s)
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("panic")
+}
+
+fn unix<S>(value: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    value
+        .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+        .map(|v| v.as_secs())
+        .serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let table = SrcTable {
+            src: Source::Acba,
+            rate_type: RateType::NoCash,
+            updated_at: Some(UNIX_EPOCH + Duration::from_secs(1700000000)),
+            rows: vec![
+                SrcRow {
+                    from: Currency::usd(),
+                    to: Currency::default(),
+                    buy: Some(dec!(386)),
+                    sell: Some(dec!(391.456)),
+                },
+                SrcRow {
+                    from: Currency::rub(),
+                    to: Currency::default(),
+                    buy: Some(dec!(4.6)),
+                    sell: None,
+                },
+            ],
+        };
+        let text = "386 | 391.46 | USD/AMD\n4.6 | -      | RUB/AMD\n";
+        assert_eq!(table.render(Format::Text, 2), text);
+        assert_eq!(
+            table.render(Format::Html, 2),
+            format!("<code>{text}</code>")
+        );
+        assert!(table.render(Format::Markdown, 2).starts_with('`'));
+        let json: serde_json::Value =
+            serde_json::from_str(&table.render(Format::Json, 2)).expect("panic");
+        assert_eq!(json["src"], "Acba");
+        assert_eq!(json["updated_at"], 1700000000);
+        assert_eq!(json["rows"][0]["from"], "USD");
+        assert_eq!(json["rows"][0]["sell"], "391.456");
+        assert_eq!(json["rows"][1]["sell"], serde_json::Value::Null);
+        let empty = SrcTable {
+            rows: vec![],
+            ..table
+        };
+        assert_eq!(empty.render(Format::Html, 2), "");
+    }
+
+    fn conv_table(amount: Option<Decimal>) -> ConvTable {
+        let row = |src, rate, diff, received| ConvRow {
+            src,
+            rate_type: RateType::NoCash,
+            rate,
+            diff,
+            received,
+            path: vec![Currency::usd(), Currency::default()],
+            updated_at: Some(UNIX_EPOCH + Duration::from_secs(1700000000)),
+        };
+        ConvTable {
+            from: Currency::usd(),
+            to: Currency::default(),
+            amount,
+            rate_type: RateType::NoCash,
+            inv: false,
+            rows: vec![
+                row(Source::Acba, dec!(387.456), Some(dec!(0)), dec!(38745.6)),
+                row(Source::Ameria, dec!(386), Some(dec!(-0.37532)), dec!(38600)),
+                row(Source::Cb, dec!(388), None, dec!(38800)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_conv_render() {
+        let table = conv_table(None);
+        let text = table.render(Format::Text, 2, 1);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("Acba   | 387.46 |    0 | USD/AMD"));
+        assert!(lines[1].ends_with("Ameria | 386    | -0.4 | USD/AMD"));
+        assert!(lines[2].ends_with("Cb     | 388    |      | USD/AMD"));
+        // the received amount is shown with an amount
+        let text = conv_table(Some(dec!(100))).render(Format::Text, 2, 1);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines[0].ends_with("Acba   | 38745.6 |     0 | USD/AMD"));
+        assert!(lines[1].ends_with("Ameria | 38600   | -0.38 | USD/AMD"));
+        let markdown = table.render(Format::Markdown, 2, 1);
+        assert!(markdown.starts_with("```\n") && markdown.ends_with("\n```"));
+        assert!(markdown.contains("| 387.46 |"));
+        let json: serde_json::Value =
+            serde_json::from_str(&table.render(Format::Json, 2, 1)).expect("panic");
+        assert_eq!(json["from"], "USD");
+        assert_eq!(json["amount"], serde_json::Value::Null);
+        assert_eq!(json["rows"][0]["src"], "Acba");
+        assert_eq!(json["rows"][0]["rate"], "387.456");
+        assert_eq!(json["rows"][1]["diff"], "-0.37532");
+        assert_eq!(json["rows"][2]["diff"], serde_json::Value::Null);
+        assert_eq!(json["rows"][0]["path"][1], "AMD");
+        assert_eq!(json["rows"][0]["updated_at"], 1700000000);
+    }
+
+    #[test]
+    fn test_conv_render_markdown_escape() {
+        let mut table = conv_table(None);
+        table.rows[0].path = vec![Currency("A`B\\C".into()), Currency::default()];
+        let markdown = table.render(Format::Markdown, 2, 1);
+        assert!(markdown.contains("| A\\`B\\\\C/AMD\n"), "{markdown}");
+        table.rows[0].path = vec![Currency("<B>".into()), Currency::default()];
+        let html = table.render(Format::Html, 2, 1);
+        assert!(html.contains("| &lt;B&gt;/AMD\n"), "{html}");
+    }
+}